@@ -14,7 +14,13 @@ pub fn run<A>(name: &str, make_app: impl FnOnce() -> anyhow::Result<A>)
 	let mut state = crate::state::init(name).expect("init failed");
 	let mut app = make_app().unwrap();
 
+	let mut last_frame = std::time::Instant::now();
+
 	'main: loop {
+		let now = std::time::Instant::now();
+		state.delta_time = (now - last_frame).as_secs_f32();
+		last_frame = now;
+
 		for event in state.event_pump.poll_iter() {
 			use sdl2::event::Event;
 			use sdl2::keyboard::Scancode;
@@ -10,6 +10,9 @@ pub struct State {
 	pub(crate) window: sdl2::video::Window,
 
 	gl_ctx: sdl2::video::GLContext,
+
+	/// Seconds since the previous frame started.
+	pub delta_time: f32,
 }
 
 impl State {
@@ -88,6 +91,8 @@ pub(crate) fn init(name: &str) -> anyhow::Result<State> {
 
 		window,
 		gl_ctx,
+
+		delta_time: 0.0,
 	})
 }
 
@@ -0,0 +1,179 @@
+//! Draws the same sprites with each `SpriteRenderer` backend, and reports how long each takes on the CPU and GPU.
+//!
+//! Opens a hidden window for its GL context, so needs a display but nothing to look at.
+//!
+//! Run with `cargo run --release -p sprite --example backend_comparison`.
+
+use anyhow::Error;
+use glam::{Vec2, Vec3, Vec4, Mat3, Mat4};
+use sprite::{Anchor, SpriteRenderer, SpriteBackend, VertexBackend, StorageBufferBackend, InstancedBackend, PointBackend,
+	CompactTexturedVertex, Sprite, Texture, TextureOptions, compile_shader, compile_shader_with_defines, check_program_status, upload_buffer};
+use std::time::Instant;
+
+
+const NUM_SPRITES: usize = 10_000;
+const NUM_FRAMES: usize = 200;
+
+
+fn main() -> anyhow::Result<()> {
+	let sdl_ctx = sdl2::init().map_err(Error::msg)?;
+	let sdl_video = sdl_ctx.video().map_err(Error::msg)?;
+
+	let gl_attr = sdl_video.gl_attr();
+	gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+	gl_attr.set_context_version(4, 5);
+
+	let window = sdl_video.window("backend comparison", 512, 512)
+		.hidden()
+		.opengl()
+		.build()?;
+
+	let gl_ctx = window.gl_create_context().map_err(Error::msg)?;
+	window.gl_make_current(&gl_ctx).map_err(Error::msg)?;
+
+	// Don't let vsync hide the difference.
+	sdl_video.gl_set_swap_interval(sdl2::video::SwapInterval::Immediate).map_err(Error::msg)?;
+
+	gl::load_with(|s| sdl_video.gl_get_proc_address(s) as *const _);
+
+	let sheet = sprite::aseprite::load_json("sprite/assets/atlas.json")?;
+	let image_path = sheet.image_path.as_ref().ok_or_else(|| Error::msg("atlas.json doesn't name its image"))?;
+	let texture = Texture::load(image_path, &TextureOptions::default())?;
+
+	let sprites: Vec<Sprite> = sheet.frames.iter().map(|frame| frame.sprite).collect();
+	let quads = generate_quads(&sprites);
+
+	let uniform_buffer = unsafe {
+		let mut handle = 0;
+		gl::CreateBuffers(1, &mut handle);
+		upload_buffer(handle, &[Mat4::orthographic_rh_gl(-60.0, 60.0, -60.0, 60.0, -60.0, 60.0)], gl::STATIC_DRAW);
+		gl::BindBufferBase(gl::UNIFORM_BUFFER, 0, handle);
+		handle
+	};
+
+	println!("{NUM_SPRITES} sprites, {NUM_FRAMES} frames\n");
+	println!("{:<40}{:>14}{:>14}{:>14}", "backend", "KiB/frame", "cpu ms/frame", "gpu ms/frame");
+
+	bench::<VertexBackend>("VertexBackend<TexturedVertex>", &texture, &quads)?;
+	bench::<VertexBackend<CompactTexturedVertex>>("VertexBackend<CompactTexturedVertex>", &texture, &quads)?;
+	bench::<StorageBufferBackend>("StorageBufferBackend", &texture, &quads)?;
+	bench::<InstancedBackend>("InstancedBackend", &texture, &quads)?;
+
+	// Not quite a fair comparison, since points ignore rotation and are clamped to the driver's maximum size.
+	bench::<PointBackend>("PointBackend", &texture, &quads)?;
+
+	unsafe {
+		gl::DeleteBuffers(1, &uniform_buffer);
+	}
+
+	Ok(())
+}
+
+
+fn bench<B: SpriteBackend>(name: &str, texture: &Texture, quads: &[(Sprite, Mat3, Vec4)]) -> anyhow::Result<()> {
+	let program = create_program(SpriteRenderer::<B>::VERTEX_SHADER, SpriteRenderer::<B>::FRAGMENT_DEFINES)?;
+	let mut renderer = SpriteRenderer::<B>::with_backend(texture);
+
+	let mut query = 0;
+	let mut cpu_seconds = 0.0;
+	let mut gpu_nanoseconds = 0;
+	let mut uploaded_bytes = 0;
+
+	unsafe {
+		gl::CreateQueries(gl::TIME_ELAPSED, 1, &mut query);
+		gl::UseProgram(program);
+	}
+
+	for _ in 0..NUM_FRAMES {
+		let start = Instant::now();
+
+		unsafe {
+			gl::Clear(gl::COLOR_BUFFER_BIT);
+			gl::BeginQuery(gl::TIME_ELAPSED, query);
+		}
+
+		for (sprite, transform, color) in quads {
+			renderer.quad_raw(sprite, transform, *color);
+		}
+
+		let stats = renderer.draw();
+		uploaded_bytes = stats.uploaded_bytes;
+
+		unsafe {
+			gl::EndQuery(gl::TIME_ELAPSED);
+
+			// Waits for the GPU to finish, so this also keeps frames from overlapping.
+			let mut elapsed = 0;
+			gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut elapsed);
+			gpu_nanoseconds += elapsed;
+		}
+
+		cpu_seconds += start.elapsed().as_secs_f64();
+	}
+
+	unsafe {
+		gl::DeleteQueries(1, &query);
+		gl::DeleteProgram(program);
+	}
+
+	println!("{:<40}{:>14.1}{:>14.3}{:>14.3}",
+		name,
+		uploaded_bytes as f32 / 1024.0,
+		cpu_seconds * 1000.0 / NUM_FRAMES as f64,
+		gpu_nanoseconds as f64 / 1.0e6 / NUM_FRAMES as f64);
+
+	Ok(())
+}
+
+
+fn create_program(vertex_shader: &str, fragment_defines: &[&str]) -> anyhow::Result<u32> {
+	unsafe {
+		let shaders = [
+			compile_shader(gl::VERTEX_SHADER, vertex_shader)?,
+			compile_shader_with_defines(gl::FRAGMENT_SHADER, include_str!("../src/shaders/frag.glsl"), fragment_defines)?,
+		];
+
+		let program = gl::CreateProgram();
+		for shader in shaders {
+			gl::AttachShader(program, shader);
+		}
+
+		gl::LinkProgram(program);
+
+		for shader in shaders {
+			gl::DetachShader(program, shader);
+			gl::DeleteShader(shader);
+		}
+
+		check_program_status(program)?;
+
+		Ok(program)
+	}
+}
+
+
+/// Deterministically scatter sprites of varying sizes, orientations and colors.
+fn generate_quads(sprites: &[Sprite]) -> Vec<(Sprite, Mat3, Vec4)> {
+	// Simple xorshift so results are reproducible without pulling in a rng crate.
+	let mut state = 0x2545_f491u32;
+	let mut next = move || {
+		state ^= state << 13;
+		state ^= state >> 17;
+		state ^= state << 5;
+		state as f32 / u32::MAX as f32
+	};
+
+	(0..NUM_SPRITES)
+		.map(|_| {
+			let sprite = sprites[(next() * sprites.len() as f32) as usize % sprites.len()];
+
+			let angle = next() * std::f32::consts::TAU;
+			let right = Vec2::from_angle(angle) * (1.0 + next() * 3.0);
+			let up = right.perp();
+			let offset = (Vec3::new(next(), next(), next()) * 2.0 - 1.0) * 50.0;
+			let color = Vec4::new(next(), next(), next(), 1.0);
+
+			(sprite.with_anchor(Anchor::BottomLeft), Mat3::from_cols(right.extend(0.0), up.extend(0.0), offset), color)
+		})
+		.collect()
+}
@@ -0,0 +1,422 @@
+use crate::Sprite;
+use crate::sprite_sheet::{SpriteSheet, TagDirection};
+
+
+#[derive(Clone, Debug)]
+pub struct AnimationFrame {
+	pub sprite: Sprite,
+
+	/// How long this frame is shown for at normal speed, in seconds.
+	pub duration: f32,
+
+	/// Names of events fired when playback enters this frame, e.g., `"footstep"`.
+	pub events: Vec<String>,
+}
+
+/// A sequence of sprites, and how to step through them.
+#[derive(Clone, Debug)]
+pub struct SpriteAnimation {
+	pub frames: Vec<AnimationFrame>,
+	pub direction: TagDirection,
+
+	/// How many passes through `frames` to play before stopping. `None` means loop forever.
+	/// As in Aseprite, each direction of a ping-pong counts as a pass.
+	pub repeat: Option<u32>,
+}
+
+impl SpriteAnimation {
+	/// A looping animation that plays `frames` forward.
+	pub fn new(frames: Vec<AnimationFrame>) -> SpriteAnimation {
+		SpriteAnimation {
+			frames,
+			direction: TagDirection::Forward,
+			repeat: None,
+		}
+	}
+
+	/// Build an animation from a tag in `sheet`, keeping its direction and repeat count.
+	pub fn from_tag(sheet: &SpriteSheet, tag_name: &str) -> Option<SpriteAnimation> {
+		let tag = sheet.tag(tag_name)?;
+
+		let frames = sheet.frames.get(tag.frames.clone())?
+			.iter()
+			.map(|frame| AnimationFrame {
+				sprite: frame.sprite,
+				duration: frame.duration,
+				events: Vec::new(),
+			})
+			.collect();
+
+		Some(SpriteAnimation {
+			frames,
+			direction: tag.direction,
+			repeat: tag.repeat,
+		})
+	}
+
+	pub fn with_direction(mut self, direction: TagDirection) -> SpriteAnimation {
+		self.direction = direction;
+		self
+	}
+
+	pub fn looping(mut self) -> SpriteAnimation {
+		self.repeat = None;
+		self
+	}
+
+	/// Play through once, then hold the last frame.
+	pub fn once(mut self) -> SpriteAnimation {
+		self.repeat = Some(1);
+		self
+	}
+
+	/// Fire `event` whenever playback enters `frame`.
+	///
+	/// # Panics
+	/// If `frame` is out of range.
+	pub fn with_event(mut self, frame: usize, event: impl Into<String>) -> SpriteAnimation {
+		let num_frames = self.frames.len();
+		let Some(frame) = self.frames.get_mut(frame) else {
+			panic!("Can't add an event to frame {frame} of an animation with {num_frames} frames");
+		};
+
+		frame.events.push(event.into());
+		self
+	}
+
+	/// The length of a single pass, in seconds.
+	pub fn duration(&self) -> f32 {
+		self.frames.iter().map(|frame| frame.duration).sum()
+	}
+}
+
+
+
+/// Steps through a `SpriteAnimation` as time is fed to it.
+///
+/// The player doesn't read any clock itself - call `update` once per tick with the frame's delta time (or
+/// anything else, in tests).
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+	animation: SpriteAnimation,
+
+	/// Multiplier applied to the time passed to `update`. Zero pauses playback.
+	pub speed: f32,
+
+	frame: usize,
+	time_in_frame: f32,
+	forwards: bool,
+	completed_passes: u32,
+	finished: bool,
+
+	/// Frames entered during the last `update`, in order - used to report events.
+	entered_frames: Vec<usize>,
+	entered_first_frame: bool,
+}
+
+impl AnimationPlayer {
+	pub fn new(animation: SpriteAnimation) -> AnimationPlayer {
+		assert!(!animation.frames.is_empty(), "Can't play an animation with no frames");
+
+		let mut player = AnimationPlayer {
+			animation,
+			speed: 1.0,
+
+			frame: 0,
+			time_in_frame: 0.0,
+			forwards: true,
+			completed_passes: 0,
+			finished: false,
+
+			entered_frames: Vec::new(),
+			entered_first_frame: false,
+		};
+
+		player.restart();
+		player
+	}
+
+	/// Switch to a different animation, starting from its beginning.
+	pub fn play(&mut self, animation: SpriteAnimation) {
+		assert!(!animation.frames.is_empty(), "Can't play an animation with no frames");
+
+		self.animation = animation;
+		self.restart();
+	}
+
+	pub fn restart(&mut self) {
+		self.forwards = matches!(self.animation.direction, TagDirection::Forward | TagDirection::PingPong);
+		self.frame = match self.forwards {
+			true => 0,
+			false => self.animation.frames.len() - 1,
+		};
+
+		self.time_in_frame = 0.0;
+		self.completed_passes = 0;
+		self.finished = false;
+
+		// Events on the first frame are reported by the next `update`.
+		self.entered_frames.clear();
+		self.entered_first_frame = false;
+	}
+
+	/// Advance playback by `dt` seconds, scaled by `speed`.
+	/// Large steps may pass through several frames, all of whose events will be reported by `events`.
+	pub fn update(&mut self, dt: f32) {
+		self.entered_frames.clear();
+
+		if !self.entered_first_frame {
+			self.entered_first_frame = true;
+			self.entered_frames.push(self.frame);
+		}
+
+		// Guard against animations that would never leave a frame.
+		if self.finished || self.animation.duration() <= 0.0 {
+			return
+		}
+
+		self.time_in_frame += dt * self.speed.max(0.0);
+
+		while self.time_in_frame >= self.animation.frames[self.frame].duration {
+			self.time_in_frame -= self.animation.frames[self.frame].duration;
+
+			if !self.advance() {
+				self.finished = true;
+				self.time_in_frame = 0.0;
+				break
+			}
+
+			self.entered_frames.push(self.frame);
+		}
+	}
+
+	/// Move to the next frame, returning false if playback has finished.
+	fn advance(&mut self) -> bool {
+		let num_frames = self.animation.frames.len();
+		let at_end = match self.forwards {
+			true => self.frame + 1 >= num_frames,
+			false => self.frame == 0,
+		};
+
+		if !at_end {
+			match self.forwards {
+				true => self.frame += 1,
+				false => self.frame -= 1,
+			}
+
+			return true
+		}
+
+		self.completed_passes += 1;
+
+		if self.animation.repeat.is_some_and(|repeat| self.completed_passes >= repeat) {
+			return false
+		}
+
+		match self.animation.direction {
+			TagDirection::Forward => self.frame = 0,
+			TagDirection::Reverse => self.frame = num_frames - 1,
+
+			// Bounce without showing the end frame twice.
+			TagDirection::PingPong | TagDirection::PingPongReverse => {
+				self.forwards = !self.forwards;
+
+				if num_frames > 1 {
+					match self.forwards {
+						true => self.frame += 1,
+						false => self.frame -= 1,
+					}
+				}
+			}
+		}
+
+		true
+	}
+
+	/// The sprite to draw this frame - pass this to `SpriteRenderer::quad_raw`.
+	pub fn sprite(&self) -> &Sprite {
+		&self.animation.frames[self.frame].sprite
+	}
+
+	/// Index into `animation().frames` of the current frame.
+	pub fn frame_index(&self) -> usize {
+		self.frame
+	}
+
+	pub fn animation(&self) -> &SpriteAnimation {
+		&self.animation
+	}
+
+	/// Whether a non-looping animation has played all of its passes. Finished players hold their last frame.
+	pub fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	/// Events fired by frames entered during the last `update`, in the order they were entered.
+	pub fn events(&self) -> impl Iterator<Item=&str> + '_ {
+		self.entered_frames.iter()
+			.flat_map(|&frame| &self.animation.frames[frame].events)
+			.map(String::as_str)
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glam::IVec2;
+
+	/// `num_frames` frames a quarter of a second long - exact in binary, so steps land exactly on frame boundaries.
+	/// Each frame fires an event named after its index.
+	fn animation(num_frames: usize, direction: TagDirection, repeat: Option<u32>) -> SpriteAnimation {
+		let frames = (0..num_frames)
+			.map(|index| AnimationFrame {
+				sprite: Sprite::new(IVec2::new(index as i32 * 8, 0), IVec2::splat(8)),
+				duration: 0.25,
+				events: vec![index.to_string()],
+			})
+			.collect();
+
+		SpriteAnimation { frames, direction, repeat }
+	}
+
+	/// The frame shown after each of `steps` updates of `dt`.
+	fn frames_after_steps(player: &mut AnimationPlayer, steps: usize, dt: f32) -> Vec<usize> {
+		(0..steps)
+			.map(|_| {
+				player.update(dt);
+				player.frame_index()
+			})
+			.collect()
+	}
+
+	fn events(player: &AnimationPlayer) -> Vec<&str> {
+		player.events().collect()
+	}
+
+	#[test]
+	fn forward() {
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::Forward, None));
+		assert_eq!(frames_after_steps(&mut player, 7, 0.25), [1, 2, 0, 1, 2, 0, 1]);
+	}
+
+	#[test]
+	fn reverse() {
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::Reverse, None));
+		assert_eq!(player.frame_index(), 2);
+		assert_eq!(frames_after_steps(&mut player, 5, 0.25), [1, 0, 2, 1, 0]);
+	}
+
+	#[test]
+	fn ping_pong() {
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::PingPong, None));
+		assert_eq!(frames_after_steps(&mut player, 8, 0.25), [1, 2, 1, 0, 1, 2, 1, 0]);
+
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::PingPongReverse, None));
+		assert_eq!(player.frame_index(), 2);
+		assert_eq!(frames_after_steps(&mut player, 6, 0.25), [1, 0, 1, 2, 1, 0]);
+
+		// A single frame has nowhere to bounce to.
+		let mut player = AnimationPlayer::new(animation(1, TagDirection::PingPong, None));
+		assert_eq!(frames_after_steps(&mut player, 3, 0.25), [0, 0, 0]);
+	}
+
+	#[test]
+	fn once_holds_last_frame() {
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::Forward, None).once());
+		assert_eq!(frames_after_steps(&mut player, 2, 0.25), [1, 2]);
+		assert!(!player.is_finished());
+
+		assert_eq!(frames_after_steps(&mut player, 3, 0.25), [2, 2, 2]);
+		assert!(player.is_finished());
+	}
+
+	#[test]
+	fn ping_pong_passes_count_each_direction() {
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::PingPong, Some(2)));
+		assert_eq!(frames_after_steps(&mut player, 6, 0.25), [1, 2, 1, 0, 0, 0]);
+		assert!(player.is_finished());
+	}
+
+	#[test]
+	fn large_steps_cross_several_frames() {
+		// Looping wraps around as many times as it needs to, reporting every frame entered.
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::Forward, None));
+		player.update(0.1);
+		player.update(1.25);
+		assert_eq!(player.frame_index(), 2);
+		assert_eq!(events(&player), ["1", "2", "0", "1", "2"]);
+
+		// Playing once stops at the end, however much time is left over.
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::Forward, None).once());
+		player.update(10.0);
+		assert_eq!(player.frame_index(), 2);
+		assert!(player.is_finished());
+		assert_eq!(events(&player), ["0", "1", "2"]);
+
+		// Repeats stop after the right number of passes.
+		let mut player = AnimationPlayer::new(animation(2, TagDirection::Forward, Some(3)));
+		player.update(1.0);
+		assert_eq!(events(&player), ["0", "1", "0", "1", "0"]);
+		assert!(!player.is_finished());
+
+		player.update(1.0);
+		assert_eq!(events(&player), ["1"]);
+		assert_eq!(player.frame_index(), 1);
+		assert!(player.is_finished());
+	}
+
+	#[test]
+	fn speed_scales_time() {
+		let mut player = AnimationPlayer::new(animation(4, TagDirection::Forward, None));
+		player.speed = 2.0;
+		assert_eq!(frames_after_steps(&mut player, 2, 0.25), [2, 0]);
+
+		player.speed = 0.5;
+		assert_eq!(frames_after_steps(&mut player, 4, 0.25), [0, 1, 1, 2]);
+
+		// Zero pauses, and negative speeds are treated as zero rather than playing backwards.
+		for speed in [0.0, -1.0] {
+			player.speed = speed;
+			assert_eq!(frames_after_steps(&mut player, 2, 0.25), [2, 2]);
+			assert!(events(&player).is_empty());
+		}
+	}
+
+	#[test]
+	fn events_fire_once_per_pass() {
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::Forward, None).with_event(1, "footstep"));
+
+		let mut fired = Vec::new();
+		for _ in 0..7 {
+			player.update(0.25);
+			fired.extend(player.events().map(String::from));
+		}
+
+		assert_eq!(fired, ["0", "1", "footstep", "2", "0", "1", "footstep", "2", "0", "1", "footstep"]);
+
+		// Staying on a frame doesn't fire it again.
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::Forward, None));
+		player.update(0.1);
+		assert_eq!(events(&player), ["0"]);
+		player.update(0.1);
+		assert!(events(&player).is_empty());
+
+		// Ping-pong doesn't repeat the frame it bounces on.
+		let mut player = AnimationPlayer::new(animation(3, TagDirection::PingPong, None));
+		player.update(1.0);
+		assert_eq!(events(&player), ["0", "1", "2", "1", "0"]);
+
+		// Restarting reports the first frame again.
+		player.restart();
+		player.update(0.0);
+		assert_eq!(events(&player), ["0"]);
+	}
+
+	#[test]
+	#[should_panic(expected = "frame 3 of an animation with 3 frames")]
+	fn event_on_missing_frame() {
+		animation(3, TagDirection::Forward, None).with_event(3, "footstep");
+	}
+}
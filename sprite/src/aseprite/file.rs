@@ -0,0 +1,587 @@
+use glam::IVec2;
+use image::{RgbaImage, Rgba};
+use std::io::Read;
+use std::path::Path;
+use crate::Sprite;
+use crate::sprite_sheet::{SpriteSheet, SpriteFrame, FrameTag, TagDirection, Slice, SliceKey};
+
+
+/// Load a `.aseprite`/`.ase` file directly, compositing the visible layers of each frame and packing the results
+/// into a single sheet image.
+///
+/// Frames are named `"{file stem} {frame index}"`, matching what Aseprite's json export produces by default
+/// minus the extension. The returned image is top row first, as `Texture::from_image` expects.
+///
+/// Every frame is kept at the full canvas size, laid out in a grid - they aren't trimmed, deduplicated or run through a
+/// `RectPacker`. For a tighter sheet, export the frames from Aseprite and pack them with `atlas_packer`.
+pub fn load(path: impl AsRef<Path>) -> anyhow::Result<(SpriteSheet, RgbaImage)> {
+	let path = path.as_ref();
+	let data = std::fs::read(path)?;
+	let title = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+
+	parse(&data, &title)
+		.map_err(|err| anyhow::anyhow!("Failed to load '{}': {err}", path.display()))
+}
+
+/// Parse the contents of a `.aseprite`/`.ase` file. See `load`.
+pub fn parse(data: &[u8], title: &str) -> anyhow::Result<(SpriteSheet, RgbaImage)> {
+	let document = Document::parse(data)?;
+
+	let frame_size = document.size;
+	let num_frames = document.frames.len() as i32;
+
+	// Lay frames out in a roughly square grid. Every frame is the same size so there's nothing to gain from
+	// anything smarter, and this keeps frame positions predictable.
+	let columns = (num_frames as f32).sqrt().ceil().max(1.0) as i32;
+	let rows = (num_frames + columns - 1) / columns;
+	let sheet_size = frame_size * IVec2::new(columns, rows);
+
+	let mut sheet_image = RgbaImage::new(sheet_size.x as u32, sheet_size.y as u32);
+	let mut frames = Vec::with_capacity(document.frames.len());
+
+	for (frame_index, frame) in document.frames.iter().enumerate() {
+		let cell = IVec2::new(frame_index as i32 % columns, frame_index as i32 / columns);
+		let position = cell * frame_size;
+
+		let frame_image = document.composite_frame(frame_index);
+		image::imageops::replace(&mut sheet_image, &frame_image, position.x as i64, position.y as i64);
+
+		// `position` is measured from the top-left, but sprites are measured from the bottom-left.
+		let start = IVec2::new(position.x, sheet_size.y - position.y - frame_size.y);
+
+		frames.push(SpriteFrame {
+			name: format!("{title} {frame_index}"),
+			sprite: Sprite::new(start, frame_size),
+			duration: frame.duration_ms as f32 / 1000.0,
+			source_size: frame_size,
+			trim_offset: IVec2::ZERO,
+			pivot: None,
+		});
+	}
+
+	let mut sheet = SpriteSheet::new(sheet_size, frames, document.tags, document.slices);
+	sheet.apply_slice_pivots();
+
+	Ok((sheet, sheet_image))
+}
+
+
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+const CHUNK_SLICE: u16 = 0x2022;
+
+const HEADER_FLAG_LAYER_OPACITY_VALID: u32 = 1;
+const LAYER_FLAG_VISIBLE: u16 = 1;
+const LAYER_FLAG_BACKGROUND: u16 = 8;
+
+const LAYER_TYPE_NORMAL: u16 = 0;
+
+const CEL_TYPE_RAW: u16 = 0;
+const CEL_TYPE_LINKED: u16 = 1;
+const CEL_TYPE_COMPRESSED: u16 = 2;
+
+const SLICE_FLAG_NINE_PATCH: u32 = 1;
+const SLICE_FLAG_PIVOT: u32 = 2;
+
+
+/// The parts of an aseprite file we care about.
+/// See <https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md> for the full format.
+struct Document {
+	size: IVec2,
+	color_depth: u16,
+	transparent_index: u8,
+
+	layers: Vec<Layer>,
+	frames: Vec<Frame>,
+	palette: Vec<Rgba<u8>>,
+	tags: Vec<FrameTag>,
+	slices: Vec<Slice>,
+}
+
+struct Layer {
+	visible: bool,
+	/// Background layers are opaque, so don't treat any palette index as transparent.
+	background: bool,
+	layer_type: u16,
+	child_level: u16,
+	opacity: u8,
+}
+
+struct Frame {
+	duration_ms: u16,
+	cels: Vec<Cel>,
+}
+
+struct Cel {
+	layer_index: usize,
+	position: IVec2,
+	opacity: u8,
+	content: CelContent,
+}
+
+enum CelContent {
+	/// Pixels in the file's color depth, top row first. `data` is empty if either dimension of `size` is zero.
+	Pixels { size: IVec2, data: Vec<u8> },
+
+	/// Use the cel on the same layer of another frame.
+	Linked { frame_index: usize },
+}
+
+
+impl Document {
+	fn parse(data: &[u8]) -> anyhow::Result<Document> {
+		let mut reader = Reader::new(data);
+
+		// Header
+		let _file_size = reader.u32()?;
+		if reader.u16()? != HEADER_MAGIC {
+			anyhow::bail!("Not an aseprite file");
+		}
+
+		let num_frames = reader.u16()?;
+		let size = IVec2::new(reader.u16()? as i32, reader.u16()? as i32);
+		let color_depth = reader.u16()?;
+		let flags = reader.u32()?;
+		let _speed = reader.u16()?;
+		reader.skip(8)?;
+		let transparent_index = reader.u8()?;
+		reader.skip(3)?;
+		let _num_colors = reader.u16()?;
+		// Pixel ratio, grid, and reserved space.
+		reader.skip(2 + 8 + 84)?;
+
+		if ![8, 16, 32].contains(&color_depth) {
+			anyhow::bail!("Unsupported color depth {color_depth}");
+		}
+
+		let layer_opacity_valid = flags & HEADER_FLAG_LAYER_OPACITY_VALID != 0;
+
+		let mut document = Document {
+			size,
+			color_depth,
+			transparent_index,
+
+			layers: Vec::new(),
+			frames: Vec::with_capacity(num_frames as usize),
+			palette: Vec::new(),
+			tags: Vec::new(),
+			slices: Vec::new(),
+		};
+
+		for frame_index in 0..num_frames as usize {
+			let frame_start = reader.position;
+			let frame_bytes = reader.u32()? as usize;
+
+			if reader.u16()? != FRAME_MAGIC {
+				anyhow::bail!("Frame {frame_index} is corrupt");
+			}
+
+			let old_num_chunks = reader.u16()?;
+			let duration_ms = reader.u16()?;
+			reader.skip(2)?;
+			let num_chunks = match reader.u32()? {
+				0 => old_num_chunks as u32,
+				num_chunks => num_chunks,
+			};
+
+			let mut frame = Frame {
+				duration_ms,
+				cels: Vec::new(),
+			};
+
+			for _ in 0..num_chunks {
+				let chunk_start = reader.position;
+				let chunk_bytes = reader.u32()? as usize;
+				let chunk_type = reader.u16()?;
+
+				let chunk_data = data.get(reader.position .. chunk_start + chunk_bytes)
+					.ok_or_else(|| anyhow::anyhow!("Chunk in frame {frame_index} is truncated"))?;
+
+				let mut chunk = Reader::new(chunk_data);
+
+				match chunk_type {
+					CHUNK_LAYER => {
+						let mut layer = parse_layer(&mut chunk)?;
+						if !layer_opacity_valid {
+							layer.opacity = 255;
+						}
+
+						document.layers.push(layer);
+					}
+
+					CHUNK_CEL => frame.cels.push(parse_cel(&mut chunk, color_depth as usize / 8)?),
+					CHUNK_TAGS => document.tags = parse_tags(&mut chunk, num_frames as usize)?,
+					CHUNK_PALETTE => parse_palette(&mut chunk, &mut document.palette)?,
+					CHUNK_SLICE => document.slices.push(parse_slice(&mut chunk)?),
+
+					// Only use the old palette chunk if there's no new one, as is recommended by the spec.
+					CHUNK_OLD_PALETTE if document.palette.is_empty() => parse_old_palette(&mut chunk, &mut document.palette)?,
+
+					// Everything else is either irrelevant to us, or not supported.
+					_ => {}
+				}
+
+				reader.position = chunk_start + chunk_bytes;
+			}
+
+			document.frames.push(frame);
+			reader.position = frame_start + frame_bytes;
+		}
+
+		Ok(document)
+	}
+
+	/// Blend all visible layers of a frame together, bottom layer first.
+	///
+	/// Only normal blending is supported - layers with other blend modes are blended as if they were normal.
+	/// Cel z-indices are also ignored.
+	fn composite_frame(&self, frame_index: usize) -> RgbaImage {
+		let mut output = RgbaImage::new(self.size.x as u32, self.size.y as u32);
+
+		for (layer_index, layer) in self.layers.iter().enumerate() {
+			if layer.layer_type != LAYER_TYPE_NORMAL || !self.is_layer_visible(layer_index) {
+				continue
+			}
+
+			let Some(cel) = self.find_cel(frame_index, layer_index) else {
+				continue
+			};
+
+			let CelContent::Pixels {size, data} = &cel.content else {
+				continue
+			};
+
+			let opacity = cel.opacity as u32 * layer.opacity as u32 / 255;
+			let bytes_per_pixel = self.color_depth as usize / 8;
+
+			// `parse_cel` already dropped any data past the end of the cel, so it can't wrap onto extra rows.
+			// Indices can be past i32::MAX in a large enough cel, so find the row and column before converting.
+			let width = size.x as usize;
+
+			for (pixel_index, pixel) in data.chunks_exact(bytes_per_pixel).enumerate() {
+				let position = cel.position + IVec2::new((pixel_index % width) as i32, (pixel_index / width) as i32);
+				if position.cmplt(IVec2::ZERO).any() || position.cmpge(self.size).any() {
+					continue
+				}
+
+				let source = self.decode_pixel(pixel, layer.background);
+				let destination = output.get_pixel_mut(position.x as u32, position.y as u32);
+				*destination = blend_normal(*destination, source, opacity);
+			}
+		}
+
+		output
+	}
+
+	/// A layer is only visible if it and all of its parent groups are.
+	fn is_layer_visible(&self, layer_index: usize) -> bool {
+		let layer = &self.layers[layer_index];
+		if !layer.visible {
+			return false
+		}
+
+		// A layer's parent is the closest preceding layer with a lower child level.
+		let mut child_level = layer.child_level;
+		for parent in self.layers[..layer_index].iter().rev() {
+			if parent.child_level >= child_level {
+				continue
+			}
+
+			if !parent.visible {
+				return false
+			}
+
+			child_level = parent.child_level;
+		}
+
+		true
+	}
+
+	/// Find the cel for a layer in a frame, following links to other frames.
+	fn find_cel(&self, frame_index: usize, layer_index: usize) -> Option<&Cel> {
+		let cel = self.frames.get(frame_index)?.cels.iter()
+			.find(|cel| cel.layer_index == layer_index)?;
+
+		match cel.content {
+			// Links always point to earlier frames, so this terminates.
+			CelContent::Linked {frame_index: linked_frame} if linked_frame < frame_index => self.find_cel(linked_frame, layer_index),
+			CelContent::Linked {..} => None,
+			CelContent::Pixels {..} => Some(cel),
+		}
+	}
+
+	fn decode_pixel(&self, pixel: &[u8], background: bool) -> Rgba<u8> {
+		match *pixel {
+			[r, g, b, a] => Rgba([r, g, b, a]),
+			[value, alpha] => Rgba([value, value, value, alpha]),
+			[index] if index == self.transparent_index && !background => Rgba([0, 0, 0, 0]),
+			[index] => self.palette.get(index as usize).copied().unwrap_or(Rgba([0, 0, 0, 0])),
+			_ => unreachable!(),
+		}
+	}
+}
+
+
+/// Blend `source` over `destination` with straight (non-premultiplied) alpha.
+fn blend_normal(destination: Rgba<u8>, source: Rgba<u8>, opacity: u32) -> Rgba<u8> {
+	let source_alpha = source[3] as u32 * opacity / 255;
+	if source_alpha == 0 {
+		return destination
+	}
+
+	let destination_alpha = destination[3] as u32;
+	let output_alpha = source_alpha + destination_alpha * (255 - source_alpha) / 255;
+
+	let blend_channel = |channel: usize| {
+		let source = source[channel] as u32 * source_alpha;
+		let destination = destination[channel] as u32 * destination_alpha * (255 - source_alpha) / 255;
+		((source + destination) / output_alpha) as u8
+	};
+
+	Rgba([blend_channel(0), blend_channel(1), blend_channel(2), output_alpha as u8])
+}
+
+
+
+fn parse_layer(chunk: &mut Reader) -> anyhow::Result<Layer> {
+	let flags = chunk.u16()?;
+	let layer_type = chunk.u16()?;
+	let child_level = chunk.u16()?;
+	// Default width and height, and blend mode.
+	chunk.skip(6)?;
+	let opacity = chunk.u8()?;
+
+	Ok(Layer {
+		visible: flags & LAYER_FLAG_VISIBLE != 0,
+		background: flags & LAYER_FLAG_BACKGROUND != 0,
+		layer_type,
+		child_level,
+		opacity,
+	})
+}
+
+/// `bytes_per_pixel` bounds how much pixel data is kept, so a corrupt cel can't inflate to more than it could use.
+fn parse_cel(chunk: &mut Reader, bytes_per_pixel: usize) -> anyhow::Result<Cel> {
+	let layer_index = chunk.u16()? as usize;
+	let position = IVec2::new(chunk.i16()? as i32, chunk.i16()? as i32);
+	let opacity = chunk.u8()?;
+	let cel_type = chunk.u16()?;
+	// z-index and reserved space.
+	chunk.skip(7)?;
+
+	let content = match cel_type {
+		CEL_TYPE_RAW | CEL_TYPE_COMPRESSED => {
+			let size = IVec2::new(chunk.u16()? as i32, chunk.u16()? as i32);
+			let pixels = chunk.remaining();
+
+			// Widened first - both dimensions can be up to 65535.
+			let max_bytes = size.x as usize * size.y as usize * bytes_per_pixel;
+
+			let data = match cel_type {
+				// Only a corrupt file would have pixels for an empty cel, but there's nothing to draw either way.
+				_ if max_bytes == 0 => Vec::new(),
+				CEL_TYPE_RAW => pixels[..pixels.len().min(max_bytes)].to_vec(),
+				_ => {
+					let mut data = Vec::new();
+					flate2::read::ZlibDecoder::new(pixels).take(max_bytes as u64).read_to_end(&mut data)?;
+					data
+				}
+			};
+
+			CelContent::Pixels {size, data}
+		}
+
+		CEL_TYPE_LINKED => CelContent::Linked { frame_index: chunk.u16()? as usize },
+
+		// Tilemaps aren't supported - treat them as empty.
+		_ => CelContent::Pixels { size: IVec2::ZERO, data: Vec::new() },
+	};
+
+	Ok(Cel {layer_index, position, opacity, content})
+}
+
+fn parse_tags(chunk: &mut Reader, num_frames: usize) -> anyhow::Result<Vec<FrameTag>> {
+	let num_tags = chunk.u16()?;
+	chunk.skip(8)?;
+
+	(0..num_tags)
+		.map(|_| {
+			let from = chunk.u16()? as usize;
+			let to = chunk.u16()? as usize;
+			let direction = match chunk.u8()? {
+				1 => TagDirection::Reverse,
+				2 => TagDirection::PingPong,
+				3 => TagDirection::PingPongReverse,
+				_ => TagDirection::Forward,
+			};
+			let repeat = match chunk.u16()? {
+				0 => None,
+				repeat => Some(repeat as u32),
+			};
+			// Reserved space and tag color.
+			chunk.skip(6 + 4)?;
+			let name = chunk.string()?;
+
+			let tag = FrameTag {
+				name,
+				frames: from..=to,
+				direction,
+				repeat,
+			};
+
+			tag.check_frames(num_frames)?;
+			Ok(tag)
+		})
+		.collect()
+}
+
+fn parse_palette(chunk: &mut Reader, palette: &mut Vec<Rgba<u8>>) -> anyhow::Result<()> {
+	let new_size = chunk.u32()? as usize;
+	let first = chunk.u32()? as usize;
+	let last = chunk.u32()? as usize;
+	chunk.skip(8)?;
+
+	palette.resize(new_size.max(palette.len()), Rgba([0, 0, 0, 0]));
+
+	for index in first..=last {
+		let flags = chunk.u16()?;
+		let color = Rgba([chunk.u8()?, chunk.u8()?, chunk.u8()?, chunk.u8()?]);
+
+		// Has name
+		if flags & 1 != 0 {
+			chunk.string()?;
+		}
+
+		if let Some(entry) = palette.get_mut(index) {
+			*entry = color;
+		}
+	}
+
+	Ok(())
+}
+
+fn parse_old_palette(chunk: &mut Reader, palette: &mut Vec<Rgba<u8>>) -> anyhow::Result<()> {
+	let num_packets = chunk.u16()?;
+	let mut index = 0;
+
+	for _ in 0..num_packets {
+		index += chunk.u8()? as usize;
+		let num_colors = match chunk.u8()? {
+			0 => 256,
+			num_colors => num_colors as usize,
+		};
+
+		for _ in 0..num_colors {
+			let color = Rgba([chunk.u8()?, chunk.u8()?, chunk.u8()?, 255]);
+
+			if palette.len() <= index {
+				palette.resize(index + 1, Rgba([0, 0, 0, 0]));
+			}
+
+			palette[index] = color;
+			index += 1;
+		}
+	}
+
+	Ok(())
+}
+
+fn parse_slice(chunk: &mut Reader) -> anyhow::Result<Slice> {
+	let num_keys = chunk.u32()?;
+	let flags = chunk.u32()?;
+	chunk.skip(4)?;
+	let name = chunk.string()?;
+
+	let keys = (0..num_keys)
+		.map(|_| {
+			let frame = chunk.u32()? as usize;
+			let start = IVec2::new(chunk.i32()?, chunk.i32()?);
+			let size = IVec2::new(chunk.u32()? as i32, chunk.u32()? as i32);
+
+			let center = match flags & SLICE_FLAG_NINE_PATCH != 0 {
+				true => Some((IVec2::new(chunk.i32()?, chunk.i32()?), IVec2::new(chunk.u32()? as i32, chunk.u32()? as i32))),
+				false => None,
+			};
+
+			let pivot = match flags & SLICE_FLAG_PIVOT != 0 {
+				true => Some(IVec2::new(chunk.i32()?, chunk.i32()?)),
+				false => None,
+			};
+
+			Ok(SliceKey {frame, start, size, center, pivot})
+		})
+		.collect::<anyhow::Result<Vec<_>>>()?;
+
+	Ok(Slice {name, keys})
+}
+
+
+
+/// Reads little-endian values from a byte slice, failing instead of panicking on truncated data.
+struct Reader<'d> {
+	data: &'d [u8],
+	position: usize,
+}
+
+impl<'d> Reader<'d> {
+	fn new(data: &'d [u8]) -> Reader<'d> {
+		Reader { data, position: 0 }
+	}
+
+	fn bytes<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+		let bytes = self.data.get(self.position .. self.position + N)
+			.ok_or_else(|| anyhow::anyhow!("Unexpected end of data"))?;
+
+		self.position += N;
+		Ok(bytes.try_into().unwrap())
+	}
+
+	fn skip(&mut self, count: usize) -> anyhow::Result<()> {
+		if self.position + count > self.data.len() {
+			anyhow::bail!("Unexpected end of data");
+		}
+
+		self.position += count;
+		Ok(())
+	}
+
+	fn remaining(&self) -> &'d [u8] {
+		&self.data[self.position.min(self.data.len())..]
+	}
+
+	fn u8(&mut self) -> anyhow::Result<u8> {
+		Ok(self.bytes::<1>()?[0])
+	}
+
+	fn u16(&mut self) -> anyhow::Result<u16> {
+		self.bytes().map(u16::from_le_bytes)
+	}
+
+	fn i16(&mut self) -> anyhow::Result<i16> {
+		self.bytes().map(i16::from_le_bytes)
+	}
+
+	fn u32(&mut self) -> anyhow::Result<u32> {
+		self.bytes().map(u32::from_le_bytes)
+	}
+
+	fn i32(&mut self) -> anyhow::Result<i32> {
+		self.bytes().map(i32::from_le_bytes)
+	}
+
+	fn string(&mut self) -> anyhow::Result<String> {
+		let length = self.u16()? as usize;
+		let bytes = self.data.get(self.position .. self.position + length)
+			.ok_or_else(|| anyhow::anyhow!("Unexpected end of data"))?;
+
+		self.position += length;
+		Ok(String::from_utf8_lossy(bytes).into_owned())
+	}
+}
@@ -0,0 +1,246 @@
+use glam::{IVec2, Vec2};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::path::Path;
+use crate::Sprite;
+use crate::sprite_sheet::{SpriteSheet, SpriteFrame, FrameTag, TagDirection, Slice, SliceKey};
+
+
+/// Load sprite sheet metadata exported with `File > Export Sprite Sheet`, with either the 'Hash' or 'Array' json layout.
+/// The path to the sheet image is resolved relative to the json file.
+pub fn load_json(path: impl AsRef<Path>) -> anyhow::Result<SpriteSheet> {
+	let path = path.as_ref();
+	let json = std::fs::read_to_string(path)?;
+	let mut sheet = parse_json(&json)?;
+
+	if let (Some(image_path), Some(directory)) = (&sheet.image_path, path.parent()) {
+		sheet.image_path = Some(directory.join(image_path));
+	}
+
+	Ok(sheet)
+}
+
+/// Parse sprite sheet metadata exported by Aseprite, with either the 'Hash' or 'Array' json layout.
+pub fn parse_json(json: &str) -> anyhow::Result<SpriteSheet> {
+	let document: JsonDocument = serde_json::from_str(json)?;
+	let sheet_size = document.meta.size.to_ivec2();
+
+	let frames: Vec<(String, JsonFrame)> = match document.frames {
+		JsonFrames::Hash(frames) => frames.into_iter().collect(),
+		JsonFrames::Array(frames) => frames.into_iter().map(|frame| (frame.filename, frame.frame)).collect(),
+	};
+
+	let frames = frames.into_iter()
+		.map(|(name, frame)| frame.into_sprite_frame(name, sheet_size))
+		.collect::<anyhow::Result<Vec<_>>>()?;
+
+	let tags = document.meta.frame_tags.into_iter()
+		.map(|tag| tag.into_frame_tag(frames.len()))
+		.collect::<anyhow::Result<Vec<_>>>()?;
+
+	let slices = document.meta.slices.into_iter()
+		.map(JsonSlice::into_slice)
+		.collect();
+
+	let mut sheet = SpriteSheet::new(sheet_size, frames, tags, slices);
+	sheet.image_path = document.meta.image.map(Into::into);
+	sheet.apply_slice_pivots();
+
+	Ok(sheet)
+}
+
+
+
+#[derive(Deserialize)]
+struct JsonDocument {
+	frames: JsonFrames,
+	meta: JsonMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonFrames {
+	// Order matters here, since tags refer to frames by index.
+	Hash(IndexMap<String, JsonFrame>),
+	Array(Vec<JsonNamedFrame>),
+}
+
+#[derive(Deserialize)]
+struct JsonNamedFrame {
+	filename: String,
+
+	#[serde(flatten)]
+	frame: JsonFrame,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFrame {
+	frame: JsonRect,
+	#[serde(default)]
+	rotated: bool,
+	sprite_source_size: Option<JsonRect>,
+	source_size: Option<JsonSize>,
+	#[serde(default = "default_frame_duration")]
+	duration: u32,
+	// Not written by Aseprite, but by other packers using the same format, e.g., TexturePacker.
+	pivot: Option<JsonPivot>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonMeta {
+	image: Option<String>,
+	size: JsonSize,
+	#[serde(default)]
+	frame_tags: Vec<JsonTag>,
+	#[serde(default)]
+	slices: Vec<JsonSlice>,
+}
+
+#[derive(Deserialize)]
+struct JsonTag {
+	name: String,
+	from: usize,
+	to: usize,
+	#[serde(default)]
+	direction: JsonDirection,
+	// Aseprite writes this as a string for some reason.
+	repeat: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum JsonDirection {
+	#[default]
+	Forward,
+	Reverse,
+	Pingpong,
+	PingpongReverse,
+}
+
+#[derive(Deserialize)]
+struct JsonSlice {
+	name: String,
+	keys: Vec<JsonSliceKey>,
+}
+
+#[derive(Deserialize)]
+struct JsonSliceKey {
+	frame: usize,
+	bounds: JsonRect,
+	center: Option<JsonRect>,
+	pivot: Option<JsonPoint>,
+}
+
+#[derive(Deserialize, Copy, Clone)]
+struct JsonRect {
+	x: i32,
+	y: i32,
+	w: i32,
+	h: i32,
+}
+
+#[derive(Deserialize, Copy, Clone)]
+struct JsonSize {
+	w: i32,
+	h: i32,
+}
+
+/// A fraction of the untrimmed frame, from the top-left.
+#[derive(Deserialize, Copy, Clone)]
+struct JsonPivot {
+	x: f32,
+	y: f32,
+}
+
+#[derive(Deserialize, Copy, Clone)]
+struct JsonPoint {
+	x: i32,
+	y: i32,
+}
+
+fn default_frame_duration() -> u32 {
+	100
+}
+
+
+
+impl JsonFrame {
+	fn into_sprite_frame(self, name: String, sheet_size: IVec2) -> anyhow::Result<SpriteFrame> {
+		if self.rotated {
+			anyhow::bail!("Frame '{name}' is rotated, which isn't supported");
+		}
+
+		let JsonRect {x, y, w, h} = self.frame;
+		let size = IVec2::new(w, h);
+
+		// Aseprite measures from the top-left, but our textures are flipped so that sprites are measured from the bottom-left.
+		let start = IVec2::new(x, sheet_size.y - y - h);
+
+		let trim_offset = self.sprite_source_size.map_or(IVec2::ZERO, |rect| IVec2::new(rect.x, rect.y));
+		let source_size = self.source_size.map_or(size, JsonSize::to_ivec2);
+
+		Ok(SpriteFrame {
+			name,
+			sprite: Sprite::new(start, size),
+			duration: self.duration as f32 / 1000.0,
+			source_size,
+			trim_offset,
+			pivot: self.pivot.map(|pivot| Vec2::new(pivot.x, pivot.y)),
+		})
+	}
+}
+
+impl JsonTag {
+	fn into_frame_tag(self, num_frames: usize) -> anyhow::Result<FrameTag> {
+		let repeat = match self.repeat.as_deref() {
+			None | Some("0") => None,
+			Some(repeat) => Some(repeat.parse()?),
+		};
+
+		let direction = match self.direction {
+			JsonDirection::Forward => TagDirection::Forward,
+			JsonDirection::Reverse => TagDirection::Reverse,
+			JsonDirection::Pingpong => TagDirection::PingPong,
+			JsonDirection::PingpongReverse => TagDirection::PingPongReverse,
+		};
+
+		let tag = FrameTag {
+			name: self.name,
+			frames: self.from..=self.to,
+			direction,
+			repeat,
+		};
+
+		tag.check_frames(num_frames)?;
+		Ok(tag)
+	}
+}
+
+impl JsonSlice {
+	fn into_slice(self) -> Slice {
+		let mut keys: Vec<SliceKey> = self.keys.into_iter()
+			.map(|key| SliceKey {
+				frame: key.frame,
+				start: IVec2::new(key.bounds.x, key.bounds.y),
+				size: IVec2::new(key.bounds.w, key.bounds.h),
+				center: key.center.map(|rect| (IVec2::new(rect.x, rect.y), IVec2::new(rect.w, rect.h))),
+				pivot: key.pivot.map(|point| IVec2::new(point.x, point.y)),
+			})
+			.collect();
+
+		keys.sort_by_key(|key| key.frame);
+
+		Slice {
+			name: self.name,
+			keys,
+		}
+	}
+}
+
+impl JsonSize {
+	fn to_ivec2(self) -> IVec2 {
+		IVec2::new(self.w, self.h)
+	}
+}
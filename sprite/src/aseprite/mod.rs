@@ -0,0 +1,7 @@
+//! Importers for sprite sheets made with [Aseprite](https://www.aseprite.org/).
+
+mod file;
+mod json;
+
+pub use file::{load, parse};
+pub use json::{load_json, parse_json};
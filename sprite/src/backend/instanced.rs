@@ -0,0 +1,106 @@
+use std::ops::Range;
+use crate::upload_buffer;
+use super::{SpriteBackend, SpriteInstance, bind_instance_attributes};
+
+
+/// Draws a single static unit quad once per sprite with `glDrawElementsInstanced`, reading each sprite's
+/// `SpriteInstance` from a second vertex buffer that advances once per instance rather than once per vertex.
+///
+/// Uploads the same amount of data as `StorageBufferBackend`, but through fixed function vertex fetching.
+pub struct InstancedBackend {
+	vao: u32,
+	quad_vertex_buffer: u32,
+	quad_index_buffer: u32,
+	instance_buffer: u32,
+}
+
+const QUAD_CORNERS: [[f32; 2]; 4] = [
+	[0.0, 0.0],
+	[1.0, 0.0],
+	[1.0, 1.0],
+	[0.0, 1.0],
+];
+
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+const QUAD_BINDING: u32 = 0;
+const INSTANCE_BINDING: u32 = 1;
+
+impl SpriteBackend for InstancedBackend {
+	const VERTEX_SHADER: &'static str = include_str!("../shaders/vert-instanced.glsl");
+
+	fn new() -> InstancedBackend {
+		let mut vao = 0;
+		let mut buffers = [0; 3];
+
+		unsafe {
+			gl::CreateVertexArrays(1, &mut vao);
+			gl::CreateBuffers(buffers.len() as i32, buffers.as_mut_ptr());
+
+			let [quad_vertex_buffer, quad_index_buffer, instance_buffer] = buffers;
+
+			// The quad never changes, so upload it once.
+			upload_buffer(quad_vertex_buffer, &QUAD_CORNERS, gl::STATIC_DRAW);
+			upload_buffer(quad_index_buffer, &QUAD_INDICES, gl::STATIC_DRAW);
+
+			gl::VertexArrayElementBuffer(vao, quad_index_buffer);
+
+			// Per-vertex: the corner of the quad.
+			gl::VertexArrayVertexBuffer(vao, QUAD_BINDING, quad_vertex_buffer, 0, std::mem::size_of::<[f32; 2]>() as i32);
+			gl::EnableVertexArrayAttrib(vao, 0);
+			gl::VertexArrayAttribFormat(vao, 0, 2, gl::FLOAT, gl::FALSE, 0);
+			gl::VertexArrayAttribBinding(vao, 0, QUAD_BINDING);
+
+			// Per-instance: everything else. A divisor of 1 advances through the buffer once per instance.
+			// Matches the `a_` inputs in `vert-instanced.glsl` from location 1 onwards.
+			bind_instance_attributes(vao, INSTANCE_BINDING, instance_buffer, 1);
+			gl::VertexArrayBindingDivisor(vao, INSTANCE_BINDING, 1);
+
+			InstancedBackend {
+				vao,
+				quad_vertex_buffer,
+				quad_index_buffer,
+				instance_buffer,
+			}
+		}
+	}
+
+	fn upload(&mut self, sprites: &[SpriteInstance]) -> usize {
+		unsafe {
+			upload_buffer(self.instance_buffer, sprites, gl::STREAM_DRAW);
+		}
+
+		std::mem::size_of_val(sprites)
+	}
+
+	fn draw(&self, sprites: Range<usize>) {
+		if sprites.is_empty() {
+			return
+		}
+
+		unsafe {
+			gl::BindVertexArray(self.vao);
+
+			// The base instance offsets where per-instance attributes are read from, without affecting gl_InstanceID.
+			gl::DrawElementsInstancedBaseInstance(
+				gl::TRIANGLES,
+				QUAD_INDICES.len() as i32,
+				gl::UNSIGNED_SHORT,
+				std::ptr::null(),
+				sprites.len() as i32,
+				sprites.start as u32
+			);
+		}
+	}
+}
+
+impl Drop for InstancedBackend {
+	fn drop(&mut self) {
+		let buffers = [self.quad_vertex_buffer, self.quad_index_buffer, self.instance_buffer];
+
+		unsafe {
+			gl::DeleteBuffers(buffers.len() as i32, buffers.as_ptr());
+			gl::DeleteVertexArrays(1, &self.vao);
+		}
+	}
+}
@@ -0,0 +1,143 @@
+//! The different ways `SpriteRenderer` can get sprites to the GPU.
+//!
+//! Every backend draws the same `SpriteInstance`s, so they can be swapped without changing anything else - only the
+//! vertex shader differs. See `SpriteBackend::VERTEX_SHADER`.
+
+use glam::{Vec2, Vec3, Vec4, Mat3, IVec2};
+use std::ops::Range;
+use crate::{Sprite, SpriteVertex};
+
+mod instanced;
+mod point;
+mod storage_buffer;
+mod vertex;
+
+pub use instanced::InstancedBackend;
+pub use point::PointBackend;
+pub use storage_buffer::StorageBufferBackend;
+pub use vertex::VertexBackend;
+
+
+pub trait SpriteBackend {
+	/// Source of the vertex shader that understands this backend's data. Link it with `frag.glsl`.
+	const VERTEX_SHADER: &'static str;
+
+	/// Defines to compile `frag.glsl` with, for backends that need a different variant of it.
+	/// See `compile_shader_with_defines`.
+	const FRAGMENT_DEFINES: &'static [&'static str] = &[];
+
+	fn new() -> Self;
+
+	/// Replace the sprites available to `draw`, returning the number of bytes uploaded.
+	fn upload(&mut self, sprites: &[SpriteInstance]) -> usize;
+
+	/// Draw a range of the sprites last passed to `upload`.
+	/// Expects a shader program made with `VERTEX_SHADER`, and the sprites' texture, to already be bound.
+	fn draw(&self, sprites: Range<usize>);
+}
+
+
+
+/// Everything needed to draw a single sprite quad, laid out to match the `Sprite` struct in `vert-ssbo.glsl` under std430.
+///
+/// The quad spans 0 to 1 along `x_axis` and `y_axis` from `origin`, with the anchor already accounted for.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SpriteInstance {
+	// A `Mat3` would be laid out as three vec3s, but std430 pads each column of a mat3 to 16 bytes.
+	// Storing the columns separately makes the padding explicit, and gives us somewhere to put `layer`.
+	pub x_axis: Vec3,
+	pub layer: f32,
+	pub y_axis: Vec3,
+	_padding0: f32,
+	pub origin: Vec3,
+	_padding1: f32,
+
+	pub uv_offset: Vec2,
+	pub uv_scale: Vec2,
+	pub color: Vec4,
+}
+
+// std430 rounds struct sizes up to their largest alignment, which is 16 here.
+const _: () = assert!(std::mem::size_of::<SpriteInstance>() == 80);
+
+impl SpriteInstance {
+	/// Place `sprite` with `transform`, as described for `SpriteRenderer::quad_raw`.
+	pub fn new(sprite: &Sprite, transform: &Mat3, color: Vec4, texture_size: IVec2) -> SpriteInstance {
+		let quad_transform = *transform * Mat3::from_translation(-sprite.anchor_fraction());
+
+		let uv_offset = sprite.start.as_vec2() / texture_size.as_vec2();
+		let uv_scale = sprite.size.as_vec2() / texture_size.as_vec2();
+
+		SpriteInstance::from_quad_transform(&quad_transform, uv_offset, uv_scale, sprite.layer, color)
+	}
+
+	/// A quad spanning 0 to 1 in `quad_transform`'s local space, showing the uv rectangle from `uv_offset` to
+	/// `uv_offset + uv_scale`.
+	pub fn from_quad_transform(quad_transform: &Mat3, uv_offset: Vec2, uv_scale: Vec2, layer: u32, color: Vec4) -> SpriteInstance {
+		SpriteInstance {
+			x_axis: quad_transform.x_axis,
+			layer: layer as f32,
+			y_axis: quad_transform.y_axis,
+			_padding0: 0.0,
+			origin: quad_transform.z_axis,
+			_padding1: 0.0,
+
+			uv_offset,
+			uv_scale,
+			color,
+		}
+	}
+
+	/// The center of the quad.
+	pub fn center(&self) -> Vec3 {
+		self.origin + (self.x_axis + self.y_axis) / 2.0
+	}
+
+	/// Generate the four corners of the quad.
+	pub fn vertices<V: SpriteVertex>(&self) -> [V; 4] {
+		let positions = [
+			Vec2::new(0.0,0.0),
+			Vec2::new(0.0,1.0),
+			Vec2::new(1.0,1.0),
+			Vec2::new(1.0,0.0),
+		];
+
+		positions.map(|pos2| {
+			let position = self.x_axis * pos2.x + self.y_axis * pos2.y + self.origin;
+			let uv = pos2 * self.uv_scale + self.uv_offset;
+
+			V::from_sprite(position, uv, self.layer as u32, self.color)
+		})
+	}
+}
+
+
+/// `SpriteInstance`'s fields as vertex attributes, for backends that read it with fixed function vertex fetching.
+/// (number of elements, offset in `SpriteInstance`)
+const INSTANCE_ATTRIBUTES: [(i32, usize); 7] = [
+	(3, std::mem::offset_of!(SpriteInstance, x_axis)),
+	(1, std::mem::offset_of!(SpriteInstance, layer)),
+	(3, std::mem::offset_of!(SpriteInstance, y_axis)),
+	(3, std::mem::offset_of!(SpriteInstance, origin)),
+	(2, std::mem::offset_of!(SpriteInstance, uv_offset)),
+	(2, std::mem::offset_of!(SpriteInstance, uv_scale)),
+	(4, std::mem::offset_of!(SpriteInstance, color)),
+];
+
+/// Set up `vao` to read `SpriteInstance`s from `buffer` through `binding`, as attributes at consecutive locations
+/// from `first_location` - in the order of `SpriteInstance`'s fields, skipping padding.
+///
+/// # Safety
+/// A GL context must be current, and `vao` and `buffer` must be valid.
+unsafe fn bind_instance_attributes(vao: u32, binding: u32, buffer: u32, first_location: u32) {
+	gl::VertexArrayVertexBuffer(vao, binding, buffer, 0, std::mem::size_of::<SpriteInstance>() as i32);
+
+	for (index, (num_elements, offset)) in INSTANCE_ATTRIBUTES.into_iter().enumerate() {
+		let location = first_location + index as u32;
+
+		gl::EnableVertexArrayAttrib(vao, location);
+		gl::VertexArrayAttribFormat(vao, location, num_elements, gl::FLOAT, gl::FALSE, offset as u32);
+		gl::VertexArrayAttribBinding(vao, location, binding);
+	}
+}
@@ -0,0 +1,119 @@
+use glam::Mat4;
+use std::ops::{Range, RangeInclusive};
+use crate::upload_buffer;
+use crate::billboard::screen_pixels_per_unit;
+use super::{SpriteBackend, SpriteInstance, bind_instance_attributes};
+
+
+/// Draws each sprite as a single `GL_POINTS` vertex, expanded into a camera facing square by the rasterizer.
+/// Meant for particles and distant sprites, where uploading and transforming one vertex per sprite matters more than
+/// orientation.
+///
+/// Points are always axis aligned on screen and centered on their quad, so rotation, skew and anchors are ignored.
+/// Each point is as wide as the longer of the quad's axes, and sprites that aren't square are cropped to their
+/// aspect ratio in `frag.glsl` - link with the `POINT_SPRITE` variant, see `FRAGMENT_DEFINES`.
+///
+/// Sizes are attenuated with distance under a perspective projection, so points match the size the equivalent quad
+/// would have at its center. Drivers clamp point sizes to `point_size_range`, which can be as small as 1 pixel at
+/// the top end but is usually at least 64. Sprites that may get larger than that on screen should be drawn with one
+/// of the quad backends instead - `fits` tells whether a sprite will be drawn at its full size.
+pub struct PointBackend {
+	vao: u32,
+	buffer: u32,
+
+	point_size_range: RangeInclusive<f32>,
+}
+
+const SPRITES_BINDING: u32 = 0;
+
+/// Corresponds to `layout(location=...)` on `u_viewport_height` in `vert-point.glsl`.
+const VIEWPORT_HEIGHT_LOCATION: i32 = 0;
+
+impl PointBackend {
+	/// The range of point sizes supported by the driver, in pixels.
+	pub fn point_size_range(&self) -> RangeInclusive<f32> {
+		self.point_size_range.clone()
+	}
+
+	/// Whether `sprite` will be drawn at its full size, rather than clamped to the largest supported point size.
+	/// `projection` and `viewport_height` should match what will be used to draw it.
+	pub fn fits(&self, sprite: &SpriteInstance, projection: &Mat4, viewport_height: f32) -> bool {
+		point_size(sprite, projection, viewport_height) <= *self.point_size_range.end()
+	}
+}
+
+impl SpriteBackend for PointBackend {
+	const VERTEX_SHADER: &'static str = include_str!("../shaders/vert-point.glsl");
+	const FRAGMENT_DEFINES: &'static [&'static str] = &["POINT_SPRITE"];
+
+	fn new() -> PointBackend {
+		let mut vao = 0;
+		let mut buffer = 0;
+		let mut point_size_range = [0.0f32; 2];
+
+		unsafe {
+			gl::CreateVertexArrays(1, &mut vao);
+			gl::CreateBuffers(1, &mut buffer);
+
+			// Every attribute advances once per vertex, and there is one vertex per sprite.
+			// Matches the `a_` inputs in `vert-point.glsl`.
+			bind_instance_attributes(vao, SPRITES_BINDING, buffer, 0);
+
+			gl::GetFloatv(gl::POINT_SIZE_RANGE, point_size_range.as_mut_ptr());
+		}
+
+		PointBackend {
+			vao,
+			buffer,
+
+			point_size_range: point_size_range[0] ..= point_size_range[1],
+		}
+	}
+
+	fn upload(&mut self, sprites: &[SpriteInstance]) -> usize {
+		unsafe {
+			upload_buffer(self.buffer, sprites, gl::STREAM_DRAW);
+		}
+
+		std::mem::size_of_val(sprites)
+	}
+
+	fn draw(&self, sprites: Range<usize>) {
+		if sprites.is_empty() {
+			return
+		}
+
+		unsafe {
+			let mut viewport = [0i32; 4];
+			gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+			gl::Uniform1f(VIEWPORT_HEIGHT_LOCATION, viewport[3] as f32);
+
+			// Let the vertex shader set gl_PointSize, and measure gl_PointCoord from the bottom-left like our uvs.
+			gl::Enable(gl::PROGRAM_POINT_SIZE);
+			gl::PointParameteri(gl::POINT_SPRITE_COORD_ORIGIN, gl::LOWER_LEFT as i32);
+
+			gl::BindVertexArray(self.vao);
+			gl::DrawArrays(gl::POINTS, sprites.start as i32, sprites.len() as i32);
+
+			// Back to GL's defaults, so other point drawing isn't affected.
+			gl::Disable(gl::PROGRAM_POINT_SIZE);
+			gl::PointParameteri(gl::POINT_SPRITE_COORD_ORIGIN, gl::UPPER_LEFT as i32);
+		}
+	}
+}
+
+impl Drop for PointBackend {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteBuffers(1, &self.buffer);
+			gl::DeleteVertexArrays(1, &self.vao);
+		}
+	}
+}
+
+
+/// The size in pixels `vert-point.glsl` will ask for when drawing `sprite`, before the driver clamps it.
+fn point_size(sprite: &SpriteInstance, projection: &Mat4, viewport_height: f32) -> f32 {
+	let world_size = sprite.x_axis.length().max(sprite.y_axis.length());
+	world_size * screen_pixels_per_unit(projection, sprite.center(), viewport_height)
+}
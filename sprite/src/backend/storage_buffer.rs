@@ -0,0 +1,68 @@
+use std::ops::Range;
+use crate::upload_buffer;
+use super::{SpriteBackend, SpriteInstance};
+
+
+/// Uploads `SpriteInstance`s as-is to a shader storage buffer, and has the vertex shader pull from it directly -
+/// 'programmable vertex pulling'.
+///
+/// No vertex attributes or index buffer are involved: each sprite is drawn as 6 vertices, and the vertex shader
+/// works out which sprite and corner it is from `gl_VertexID`. Uploads one record per sprite instead of four vertices.
+pub struct StorageBufferBackend {
+	buffer: u32,
+
+	// Core profile requires a VAO to be bound to draw anything, even if it has no attributes.
+	empty_vao: u32,
+}
+
+/// Corresponds to `layout(binding=...)` on the `Sprites` buffer in `vert-ssbo.glsl`.
+const SPRITES_BINDING: u32 = 0;
+
+const VERTICES_PER_SPRITE: usize = 6;
+
+impl SpriteBackend for StorageBufferBackend {
+	const VERTEX_SHADER: &'static str = include_str!("../shaders/vert-ssbo.glsl");
+
+	fn new() -> StorageBufferBackend {
+		let mut buffer = 0;
+		let mut empty_vao = 0;
+
+		unsafe {
+			gl::CreateBuffers(1, &mut buffer);
+			gl::CreateVertexArrays(1, &mut empty_vao);
+		}
+
+		StorageBufferBackend { buffer, empty_vao }
+	}
+
+	fn upload(&mut self, sprites: &[SpriteInstance]) -> usize {
+		unsafe {
+			upload_buffer(self.buffer, sprites, gl::STREAM_DRAW);
+		}
+
+		std::mem::size_of_val(sprites)
+	}
+
+	fn draw(&self, sprites: Range<usize>) {
+		if sprites.is_empty() {
+			return
+		}
+
+		unsafe {
+			gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, SPRITES_BINDING, self.buffer);
+			gl::BindVertexArray(self.empty_vao);
+
+			// gl_VertexID starts from `first`, so the shader sees the right sprite indices without any extra uniforms.
+			gl::DrawArrays(gl::TRIANGLES, (sprites.start * VERTICES_PER_SPRITE) as i32, (sprites.len() * VERTICES_PER_SPRITE) as i32);
+		}
+	}
+}
+
+impl Drop for StorageBufferBackend {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteBuffers(1, &self.buffer);
+			gl::DeleteVertexArrays(1, &self.empty_vao);
+		}
+	}
+}
@@ -0,0 +1,71 @@
+use std::ops::Range;
+use crate::{Mesh, MeshBuilder, SpriteVertex, TexturedVertex};
+use crate::sprite_renderer::QUAD_INDICES;
+use super::{SpriteBackend, SpriteInstance};
+
+
+/// Expands each sprite into four vertices on the CPU, and uploads them as a regular indexed mesh.
+///
+/// The simplest approach, and the only one that works without any special shader support.
+/// `V` determines the vertex format - see `TexturedVertex` and `CompactTexturedVertex`.
+///
+/// Meshes use u16 indices, so sprites are split into meshes of up to `QUADS_PER_MESH`, each drawn separately.
+pub struct VertexBackend<V: SpriteVertex = TexturedVertex> {
+	/// One per `QUADS_PER_MESH` sprites. Kept around once made, even if later frames need fewer.
+	chunks: Vec<(MeshBuilder<V>, Mesh<V>)>,
+	/// How many of `chunks` hold sprites from the last `upload`.
+	used_chunks: usize,
+}
+
+impl<V: SpriteVertex> VertexBackend<V> {
+	/// The most sprites that fit in one mesh's u16 indices.
+	pub const QUADS_PER_MESH: usize = MeshBuilder::<V>::MAX_VERTICES / 4;
+}
+
+impl<V: SpriteVertex> SpriteBackend for VertexBackend<V> {
+	const VERTEX_SHADER: &'static str = include_str!("../shaders/vert.glsl");
+
+	fn new() -> VertexBackend<V> {
+		VertexBackend {
+			chunks: Vec::new(),
+			used_chunks: 0,
+		}
+	}
+
+	fn upload(&mut self, sprites: &[SpriteInstance]) -> usize {
+		let mut uploaded_bytes = 0;
+		self.used_chunks = 0;
+
+		for (chunk_index, chunk_sprites) in sprites.chunks(Self::QUADS_PER_MESH).enumerate() {
+			if chunk_index == self.chunks.len() {
+				self.chunks.push((MeshBuilder::new(), Mesh::new()));
+			}
+
+			let (builder, mesh) = &mut self.chunks[chunk_index];
+			builder.clear();
+
+			for sprite in chunk_sprites {
+				builder.append(sprite.vertices(), QUAD_INDICES);
+			}
+
+			mesh.upload(builder, gl::STREAM_DRAW);
+			self.used_chunks += 1;
+
+			uploaded_bytes += std::mem::size_of_val(builder.vertices.as_slice())
+				+ std::mem::size_of_val(builder.indices.as_slice());
+		}
+
+		uploaded_bytes
+	}
+
+	fn draw(&self, sprites: Range<usize>) {
+		for (chunk_index, (_, mesh)) in self.chunks[..self.used_chunks].iter().enumerate() {
+			// The part of `sprites` in this chunk, relative to its first sprite.
+			let chunk_start = chunk_index * Self::QUADS_PER_MESH;
+			let start = sprites.start.clamp(chunk_start, chunk_start + Self::QUADS_PER_MESH) - chunk_start;
+			let end = sprites.end.clamp(chunk_start, chunk_start + Self::QUADS_PER_MESH) - chunk_start;
+
+			mesh.draw_range(start * QUAD_INDICES.len() .. end * QUAD_INDICES.len());
+		}
+	}
+}
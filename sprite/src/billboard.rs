@@ -0,0 +1,80 @@
+use glam::{Vec3, Vec2, Mat3, Mat4};
+
+
+/// How a billboarded sprite turns to face the camera. See `SpriteRenderer::billboard`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Billboard {
+	/// Faces the camera's position from every direction, like a particle.
+	/// Stays upright unless viewed from directly above or below.
+	Spherical,
+
+	/// Only rotates around world up (+Y) to face the camera's position, like a tree or a character in a 2.5D game.
+	/// Foreshortens when viewed from above.
+	Cylindrical,
+
+	/// Parallel to the screen, so every sprite shares the camera's orientation no matter where it is.
+	/// Unlike `Spherical`, sprites don't distort towards the edges of a wide field of view.
+	ScreenAligned,
+}
+
+/// How big a billboard is drawn. See `SpriteRenderer::billboard`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BillboardSize {
+	/// The sprite's pixel size divided by the renderer's pixels per unit, times this scale.
+	/// Shrinks with distance like any other geometry.
+	World(f32),
+
+	/// Each pixel of the sprite covers this many pixels on screen, regardless of distance.
+	/// Whole numbers keep pixel art crisp.
+	Screen(f32),
+
+	/// Exactly this many world units, ignoring the sprite's pixel size.
+	Fixed(Vec2),
+}
+
+
+impl Billboard {
+	/// The transform for a quad of `size` world units at `position`, facing a camera placed by `camera_transform`,
+	/// which is the inverse of the view matrix. The result can be passed straight to `SpriteRenderer::quad_raw`.
+	pub fn transform(self, camera_transform: &Mat4, position: Vec3, size: Vec2) -> Mat3 {
+		let camera_right = camera_transform.x_axis.truncate().normalize();
+		let camera_up = camera_transform.y_axis.truncate().normalize();
+		let camera_back = camera_transform.z_axis.truncate().normalize();
+		let camera_position = camera_transform.w_axis.truncate();
+
+		let (right, up) = match self {
+			Billboard::ScreenAligned => (camera_right, camera_up),
+
+			Billboard::Spherical => {
+				// When looking straight down or up, world up is useless for orienting the quad - use the camera's instead.
+				let forward = (camera_position - position).try_normalize().unwrap_or(camera_back);
+				let right = Vec3::Y.cross(forward).try_normalize().unwrap_or(camera_right);
+				(right, forward.cross(right))
+			}
+
+			Billboard::Cylindrical => {
+				let to_camera = camera_position - position;
+				let forward = Vec3::new(to_camera.x, 0.0, to_camera.z).try_normalize()
+					.or_else(|| Vec3::new(camera_back.x, 0.0, camera_back.z).try_normalize())
+					.unwrap_or(Vec3::Z);
+
+				(Vec3::Y.cross(forward), Vec3::Y)
+			}
+		};
+
+		Mat3::from_cols(right * size.x, up * size.y, position)
+	}
+}
+
+
+/// How many pixels on screen a world unit at `position` covers, measured parallel to the screen. Assumes the view
+/// part of `view_projection` doesn't scale.
+pub(crate) fn screen_pixels_per_unit(view_projection: &Mat4, position: Vec3, viewport_height: f32) -> f32 {
+	let clip_position = *view_projection * position.extend(1.0);
+
+	// How much clip space y changes per world unit, regardless of which way the camera is facing.
+	// For both perspective and orthographic projections this is the length of the second row.
+	let clip_scale = view_projection.row(1).truncate().length();
+
+	clip_scale * viewport_height / 2.0 / clip_position.w
+}
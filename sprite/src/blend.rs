@@ -0,0 +1,167 @@
+use crate::{SpriteBackend, compile_shader, compile_shader_with_defines, check_program_status};
+
+
+/// How a sprite's fragments are combined with what's already been drawn.
+///
+/// Each mode needs both its GL blend state, set by `apply`, and its variant of `frag.glsl`, compiled with `define` -
+/// `SpriteShaders` builds all of them. `frag.glsl` compiled without any of these defines behaves as `Dithered`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BlendMode {
+	/// No blending. Fragments with less than half alpha are discarded, and the rest are drawn fully opaque.
+	Opaque,
+
+	/// Alpha is approximated by discarding a pattern of fragments, so sprites can still be depth tested against each
+	/// other in any order. Looks grainy at partial alpha, but never needs sorting.
+	/// The pattern is picked with `SpriteRenderer::set_dither`.
+	#[default]
+	Dithered,
+
+	/// Standard 'over' blending, for textures with straight (unpremultiplied) alpha.
+	Alpha,
+
+	/// 'Over' blending for textures whose color channels have already been multiplied by alpha.
+	/// Avoids dark fringes around filtered edges.
+	PremultipliedAlpha,
+
+	/// Adds color to what's behind, scaled by alpha. For glows, fire and light.
+	Additive,
+
+	/// Multiplies what's behind by the sprite's color, fading to no change as alpha goes to zero. For shadows and tints.
+	Multiply,
+}
+
+impl BlendMode {
+	pub const ALL: [BlendMode; 6] = [
+		BlendMode::Opaque,
+		BlendMode::Dithered,
+		BlendMode::Alpha,
+		BlendMode::PremultipliedAlpha,
+		BlendMode::Additive,
+		BlendMode::Multiply,
+	];
+
+	/// Whether sprites in this mode show what's behind them, and so need to be drawn back-to-front after everything
+	/// opaque, without writing depth.
+	pub fn is_transparent(self) -> bool {
+		!matches!(self, BlendMode::Opaque | BlendMode::Dithered)
+	}
+
+	/// The define that selects this mode's variant of `frag.glsl`.
+	pub fn define(self) -> &'static str {
+		match self {
+			BlendMode::Opaque => "BLEND_OPAQUE",
+			BlendMode::Dithered => "BLEND_DITHERED",
+			BlendMode::Alpha => "BLEND_ALPHA",
+			BlendMode::PremultipliedAlpha => "BLEND_PREMULTIPLIED_ALPHA",
+			BlendMode::Additive => "BLEND_ADDITIVE",
+			BlendMode::Multiply => "BLEND_MULTIPLY",
+		}
+	}
+
+	/// Set GL blend state for this mode. Depth writes are left to the caller.
+	pub fn apply(self) {
+		// Alpha is accumulated with 'over' where it makes sense, so the framebuffer's alpha stays meaningful
+		// if it's composited later.
+		let funcs = match self {
+			BlendMode::Opaque | BlendMode::Dithered => None,
+			BlendMode::Alpha => Some((gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA)),
+			BlendMode::PremultipliedAlpha => Some((gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA)),
+			BlendMode::Additive => Some((gl::SRC_ALPHA, gl::ONE, gl::ZERO, gl::ONE)),
+			BlendMode::Multiply => Some((gl::DST_COLOR, gl::ZERO, gl::ZERO, gl::ONE)),
+		};
+
+		unsafe {
+			match funcs {
+				Some((src_color, dst_color, src_alpha, dst_alpha)) => {
+					gl::Enable(gl::BLEND);
+					gl::BlendEquation(gl::FUNC_ADD);
+					gl::BlendFuncSeparate(src_color, dst_color, src_alpha, dst_alpha);
+				}
+
+				None => gl::Disable(gl::BLEND),
+			}
+		}
+	}
+}
+
+
+
+/// A shader program for each `BlendMode`, made from a backend's vertex shader and the matching variants of `frag.glsl`.
+/// Pass to `SpriteRenderer::set_shaders` to have it switch between them as it draws.
+pub struct SpriteShaders {
+	programs: [u32; BlendMode::ALL.len()],
+}
+
+impl SpriteShaders {
+	pub fn new<B: SpriteBackend>() -> anyhow::Result<SpriteShaders> {
+		SpriteShaders::with_fragment_defines(B::VERTEX_SHADER, B::FRAGMENT_DEFINES)
+	}
+
+	/// Link `vertex_shader` with each blend mode's variant of `frag.glsl`, each also compiled with `fragment_defines`,
+	/// e.g., `ARRAY_TEXTURE`.
+	pub fn with_fragment_defines(vertex_shader: &str, fragment_defines: &[&str]) -> anyhow::Result<SpriteShaders> {
+		let mut shaders = SpriteShaders { programs: [0; BlendMode::ALL.len()] };
+
+		for mode in BlendMode::ALL {
+			let defines: Vec<&str> = fragment_defines.iter().copied()
+				.chain(std::iter::once(mode.define()))
+				.collect();
+
+			// If this fails, dropping `shaders` cleans up the programs made so far.
+			shaders.programs[mode as usize] = link_program(vertex_shader, &defines)?;
+		}
+
+		Ok(shaders)
+	}
+
+	pub fn program(&self, mode: BlendMode) -> u32 {
+		self.programs[mode as usize]
+	}
+
+	pub(crate) fn programs(&self) -> [u32; BlendMode::ALL.len()] {
+		self.programs
+	}
+}
+
+impl Drop for SpriteShaders {
+	fn drop(&mut self) {
+		for program in self.programs {
+			unsafe {
+				// Zero is silently ignored.
+				gl::DeleteProgram(program);
+			}
+		}
+	}
+}
+
+
+fn link_program(vertex_shader: &str, fragment_defines: &[&str]) -> anyhow::Result<u32> {
+	let vertex = compile_shader(gl::VERTEX_SHADER, vertex_shader)?;
+
+	let fragment = match compile_shader_with_defines(gl::FRAGMENT_SHADER, include_str!("shaders/frag.glsl"), fragment_defines) {
+		Ok(fragment) => fragment,
+		Err(error) => {
+			unsafe { gl::DeleteShader(vertex); }
+			return Err(error)
+		}
+	};
+
+	unsafe {
+		let program = gl::CreateProgram();
+		gl::AttachShader(program, vertex);
+		gl::AttachShader(program, fragment);
+		gl::LinkProgram(program);
+
+		for shader in [vertex, fragment] {
+			gl::DetachShader(program, shader);
+			gl::DeleteShader(shader);
+		}
+
+		if let Err(error) = check_program_status(program) {
+			gl::DeleteProgram(program);
+			return Err(error)
+		}
+
+		Ok(program)
+	}
+}
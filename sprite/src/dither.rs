@@ -0,0 +1,462 @@
+use glam::IVec2;
+use std::sync::OnceLock;
+use crate::{Texture, TextureFormat, TextureOptions};
+
+
+/// The thresholds `BlendMode::Dithered` compares alpha against - a square tile, repeated across its `DitherSpace`.
+///
+/// ```
+/// # use sprite::DitherPattern;
+/// // The classic ordered dither matrix, bottom row first.
+/// let ranks: Vec<f32> = DitherPattern::Bayer4x4.thresholds().iter().map(|threshold| threshold * 16.0 - 0.5).collect();
+///
+/// assert_eq!(ranks, [
+///     0.0, 8.0, 2.0, 10.0,
+///     12.0, 4.0, 14.0, 6.0,
+///     3.0, 11.0, 1.0, 9.0,
+///     15.0, 7.0, 13.0, 5.0,
+/// ]);
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DitherPattern {
+	/// Ordered dithering with a 2x2 Bayer matrix. Only five levels of alpha, but too fine to see a pattern in.
+	#[default]
+	Bayer2x2,
+
+	/// 17 levels of alpha, in a visible but regular cross-hatch.
+	Bayer4x4,
+
+	/// 65 levels of alpha, for slow fades.
+	Bayer8x8,
+
+	/// A 64x64 tile of blue noise. Grainier than the Bayer matrices, but without any regular structure to pick out.
+	BlueNoise,
+}
+
+/// What a `DitherPattern` is anchored to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DitherSpace {
+	/// Fixed to the screen. Crisp, but the pattern "swims" across sprites as they or the camera move.
+	#[default]
+	Screen,
+
+	/// Fixed in the world, projected onto whichever axis-aligned plane each sprite faces most.
+	/// Stays put as the camera moves, but not as the sprite does.
+	/// `PointBackend`'s fragments have no world position, so it uses `Object` instead.
+	World,
+
+	/// Fixed to each sprite's texels, so the pattern moves, scales and turns with the sprite.
+	Object,
+}
+
+/// How `BlendMode::Dithered` sprites approximate partial alpha - see `SpriteRenderer::set_dither`.
+///
+/// ```
+/// # use glam::IVec2;
+/// # use sprite::{Dither, DitherPattern, DitherSpace};
+/// // Two cells to each sprite texel, shifted every frame.
+/// let dither = Dither::new(DitherPattern::Bayer8x8)
+///     .with_space(DitherSpace::Object)
+///     .with_density(2.0)
+///     .with_temporal(true);
+///
+/// let size = DitherPattern::Bayer8x8.size() as i32;
+/// assert_eq!(dither.offset(0), IVec2::ZERO);
+///
+/// for frame in [1, 2, 3, 1000, u32::MAX] {
+///     let offset = dither.offset(frame);
+///     assert!(offset.cmpge(IVec2::ZERO).all() && offset.cmplt(IVec2::splat(size)).all());
+/// }
+///
+/// // Consecutive frames never reuse an offset.
+/// assert_ne!(dither.offset(1), dither.offset(2));
+/// assert_eq!(dither.with_temporal(false).offset(1), IVec2::ZERO);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Dither {
+	pub pattern: DitherPattern,
+	pub space: DitherSpace,
+
+	/// Pattern cells per unit of `space` - per screen pixel, world unit or sprite texel.
+	pub density: f32,
+
+	/// Shift the pattern each frame, so a partially transparent pixel averages out to its alpha over several frames
+	/// rather than being stuck on or off. Reads as flicker unless smoothed out by temporal anti-aliasing.
+	pub temporal: bool,
+}
+
+impl DitherPattern {
+	pub const ALL: [DitherPattern; 4] = [
+		DitherPattern::Bayer2x2,
+		DitherPattern::Bayer4x4,
+		DitherPattern::Bayer8x8,
+		DitherPattern::BlueNoise,
+	];
+
+	/// Width and height of the tile, always a power of two.
+	pub fn size(self) -> usize {
+		match self {
+			DitherPattern::Bayer2x2 => 2,
+			DitherPattern::Bayer4x4 => 4,
+			DitherPattern::Bayer8x8 => 8,
+			DitherPattern::BlueNoise => 64,
+		}
+	}
+
+	/// The threshold of each cell, row by row from the bottom-left, so cell `(x, y)` is at `x + y * size`.
+	/// Fragments with less alpha than their cell's threshold are discarded.
+	///
+	/// Each cell has a distinct rank, and a threshold of `(rank + 0.5) / cells` - halfway between the alpha levels the
+	/// pattern can show, so interpolated alpha that's meant to sit exactly on a level can't flicker across it.
+	pub fn thresholds(self) -> Vec<f32> {
+		let ranks = self.ranks();
+		let cells = ranks.len() as f32;
+		ranks.into_iter().map(|rank| (rank as f32 + 0.5) / cells).collect()
+	}
+
+	/// Each cell's position in the order cells are kept as alpha rises, laid out like `thresholds`.
+	fn ranks(self) -> Vec<u32> {
+		match self {
+			DitherPattern::Bayer2x2 | DitherPattern::Bayer4x4 | DitherPattern::Bayer8x8 => bayer_ranks(self.size()),
+
+			DitherPattern::BlueNoise => {
+				// Takes seconds in a debug build, so only done once.
+				static BLUE_NOISE: OnceLock<Vec<u32>> = OnceLock::new();
+				BLUE_NOISE.get_or_init(|| blue_noise_ranks(self.size())).clone()
+			}
+		}
+	}
+
+	/// `thresholds` in a single channel texture, to bind to `u_dither_thresholds` in frag.glsl.
+	pub(crate) fn texture(self) -> Texture {
+		let options = TextureOptions {
+			format: TextureFormat::SingleChannel16,
+			..TextureOptions::default()
+		};
+
+		Texture::from_pixels(IVec2::splat(self.size() as i32), &self.texture_pixels(), &options)
+	}
+
+	/// `thresholds` as 16 bit texels - 8 bits would merge blue noise's 4096 levels down to 256.
+	fn texture_pixels(self) -> Vec<u8> {
+		self.thresholds().into_iter()
+			.flat_map(|threshold| ((threshold * u16::MAX as f32).round() as u16).to_ne_bytes())
+			.collect()
+	}
+}
+
+impl Dither {
+	/// `pattern` fixed to the screen, one cell per pixel.
+	pub fn new(pattern: DitherPattern) -> Dither {
+		Dither {
+			pattern,
+			space: DitherSpace::Screen,
+			density: 1.0,
+			temporal: false,
+		}
+	}
+
+	pub fn with_space(mut self, space: DitherSpace) -> Dither {
+		self.space = space;
+		self
+	}
+
+	pub fn with_density(mut self, density: f32) -> Dither {
+		self.density = density;
+		self
+	}
+
+	pub fn with_temporal(mut self, temporal: bool) -> Dither {
+		self.temporal = temporal;
+		self
+	}
+
+	/// How many cells to shift the pattern by on frame number `frame`. Always zero unless `temporal` is set.
+	pub fn offset(&self, frame: u32) -> IVec2 {
+		if !self.temporal {
+			return IVec2::ZERO;
+		}
+
+		// The R2 sequence, which spreads consecutive offsets evenly over the tile. Fractions are kept as 32 bit fixed
+		// point, so the sequence wraps around cleanly rather than losing precision as `frame` grows.
+		let fraction_x = frame.wrapping_mul(3_242_174_889);
+		let fraction_y = frame.wrapping_mul(2_447_445_414);
+
+		let size = self.pattern.size() as u64;
+		let cell = |fraction: u32| ((fraction as u64 * size) >> 32) as i32;
+
+		IVec2::new(cell(fraction_x), cell(fraction_y))
+	}
+}
+
+impl Default for Dither {
+	fn default() -> Dither {
+		Dither::new(DitherPattern::default())
+	}
+}
+
+
+/// Each pattern's threshold texture, made the first time it's drawn with.
+#[derive(Default)]
+pub(crate) struct DitherTextures {
+	textures: [Option<Texture>; DitherPattern::ALL.len()],
+}
+
+impl DitherTextures {
+	/// Bind `dither`'s thresholds and set the uniforms in frag.glsl to match, for the program currently bound.
+	/// `frame` counts frames drawn, for `Dither::temporal`.
+	pub(crate) fn bind(&mut self, dither: &Dither, frame: u32) {
+		let texture = self.textures[dither.pattern as usize].get_or_insert_with(|| dither.pattern.texture());
+		let offset = dither.offset(frame);
+
+		// Matching the bindings and locations in frag.glsl
+		texture.bind(1);
+
+		unsafe {
+			gl::Uniform1i(1, dither.pattern.size() as i32);
+			gl::Uniform1i(2, dither.space as i32);
+			gl::Uniform1f(3, dither.density);
+			gl::Uniform2i(4, offset.x, offset.y);
+		}
+	}
+}
+
+
+/// Ranks of a `size`x`size` Bayer matrix, built up by doubling: each cell of the smaller matrix becomes a 2x2 block
+/// whose ranks are spread out in the same order as the 2x2 matrix's own.
+fn bayer_ranks(size: usize) -> Vec<u32> {
+	const BAYER_2X2: [u32; 4] = [0, 2, 3, 1];
+
+	let mut ranks = vec![0];
+	let mut current = 1;
+
+	while current < size {
+		let next = current * 2;
+
+		ranks = (0..next * next)
+			.map(|index| {
+				let (x, y) = (index % next, index / next);
+				let parent = ranks[x % current + (y % current) * current];
+				4 * parent + BAYER_2X2[x / current + (y / current) * 2]
+			})
+			.collect();
+
+		current = next;
+	}
+
+	ranks
+}
+
+
+/// Ranks of a `size`x`size` tile of blue noise, by void-and-cluster.
+///
+/// Points are settled into an even spread, then ranked by taking them away one at a time from wherever they're most
+/// crowded, and adding the rest one at a time wherever is emptiest - so every alpha level is evenly spread too.
+fn blue_noise_ranks(size: usize) -> Vec<u32> {
+	let cells = size * size;
+	let mut field = EnergyField::new(size);
+
+	// A fixed seed, so the tile is the same every run.
+	let mut random = 0x2545_f491_u32;
+	let mut initial_points = 0;
+
+	while initial_points < cells / 10 {
+		random ^= random << 13;
+		random ^= random >> 17;
+		random ^= random << 5;
+
+		let index = random as usize % cells;
+		if !field.points[index] {
+			field.toggle(index);
+			initial_points += 1;
+		}
+	}
+
+	// Move the most crowded point to the emptiest spot, until it would just move straight back.
+	for _ in 0..cells {
+		let cluster = field.tightest_cluster();
+		field.toggle(cluster);
+
+		let void = field.largest_void();
+		field.toggle(void);
+
+		if void == cluster {
+			break;
+		}
+	}
+
+	let mut ranks = vec![0; cells];
+
+	let mut removing = field.clone();
+	for rank in (0..initial_points).rev() {
+		let cluster = removing.tightest_cluster();
+		removing.toggle(cluster);
+		ranks[cluster] = rank as u32;
+	}
+
+	// Past half full this is usually done by ranking the emptiest cells' tightest clusters instead, but each cell's
+	// energy from empty cells is a constant minus its energy from points, so that picks the same cells.
+	for rank in initial_points..cells {
+		let void = field.largest_void();
+		field.toggle(void);
+		ranks[void] = rank as u32;
+	}
+
+	ranks
+}
+
+/// Points on a wrapping grid, and how crowded each cell is by them.
+#[derive(Clone)]
+struct EnergyField {
+	size: usize,
+	points: Vec<bool>,
+	energy: Vec<f32>,
+
+	/// Each point's contribution to the energy of a cell `(x, y)` away from it, indexed like `points`.
+	kernel: Vec<f32>,
+}
+
+impl EnergyField {
+	fn new(size: usize) -> EnergyField {
+		const SIGMA: f32 = 1.5;
+
+		let kernel = (0..size * size)
+			.map(|index| {
+				// The distance the short way round, so the tile repeats seamlessly.
+				let wrap = |offset: usize| offset.min(size - offset) as f32;
+				let (x, y) = (wrap(index % size), wrap(index / size));
+				(-(x * x + y * y) / (2.0 * SIGMA * SIGMA)).exp()
+			})
+			.collect();
+
+		EnergyField {
+			size,
+			points: vec![false; size * size],
+			energy: vec![0.0; size * size],
+			kernel,
+		}
+	}
+
+	/// Add a point at `index`, or remove it if there already is one.
+	fn toggle(&mut self, index: usize) {
+		let sign = if self.points[index] { -1.0 } else { 1.0 };
+		self.points[index] = !self.points[index];
+
+		let size = self.size;
+		let (point_x, point_y) = (index % size, index / size);
+
+		for (cell, energy) in self.energy.iter_mut().enumerate() {
+			let x = (cell % size + size - point_x) % size;
+			let y = (cell / size + size - point_y) % size;
+			*energy += sign * self.kernel[x + y * size];
+		}
+	}
+
+	/// The point with the most energy.
+	fn tightest_cluster(&self) -> usize {
+		self.extreme(true, |a, b| a > b)
+	}
+
+	/// The empty cell with the least energy.
+	fn largest_void(&self) -> usize {
+		self.extreme(false, |a, b| a < b)
+	}
+
+	/// The cell with or without a point whose energy is `better` than all others, taking the first on ties.
+	fn extreme(&self, point: bool, better: impl Fn(f32, f32) -> bool) -> usize {
+		self.energy.iter().enumerate()
+			.filter(|&(index, _)| self.points[index] == point)
+			.reduce(|best, next| if better(*next.1, *best.1) { next } else { best })
+			.map(|(index, _)| index)
+			.expect("grid is entirely full or empty")
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bayer_matches_reference() {
+		assert_eq!(DitherPattern::Bayer2x2.ranks(), [
+			0, 2,
+			3, 1,
+		]);
+
+		assert_eq!(DitherPattern::Bayer4x4.ranks(), [
+			0, 8, 2, 10,
+			12, 4, 14, 6,
+			3, 11, 1, 9,
+			15, 7, 13, 5,
+		]);
+
+		assert_eq!(DitherPattern::Bayer8x8.ranks(), [
+			0, 32, 8, 40, 2, 34, 10, 42,
+			48, 16, 56, 24, 50, 18, 58, 26,
+			12, 44, 4, 36, 14, 46, 6, 38,
+			60, 28, 52, 20, 62, 30, 54, 22,
+			3, 35, 11, 43, 1, 33, 9, 41,
+			51, 19, 59, 27, 49, 17, 57, 25,
+			15, 47, 7, 39, 13, 45, 5, 37,
+			63, 31, 55, 23, 61, 29, 53, 21,
+		]);
+	}
+
+	#[test]
+	fn ranks_are_permutations() {
+		for pattern in DitherPattern::ALL {
+			let cells = (pattern.size() * pattern.size()) as u32;
+			let mut ranks = pattern.ranks();
+			ranks.sort();
+
+			assert!(ranks.into_iter().eq(0..cells), "{pattern:?} doesn't rank every cell exactly once");
+		}
+	}
+
+	#[test]
+	fn each_alpha_level_keeps_one_more_cell() {
+		for pattern in DitherPattern::ALL {
+			let thresholds = pattern.thresholds();
+			let cells = thresholds.len();
+
+			for kept in 0..=cells {
+				let alpha = kept as f32 / cells as f32;
+				assert_eq!(thresholds.iter().filter(|&&threshold| alpha >= threshold).count(), kept, "{pattern:?} at {alpha}");
+			}
+		}
+	}
+
+	#[test]
+	fn blue_noise_is_spread_out() {
+		// The first cells to be kept are spread out, even across the edges of the tile.
+		let size = DitherPattern::BlueNoise.size() as i32;
+		let kept: Vec<(i32, i32)> = DitherPattern::BlueNoise.ranks().iter().enumerate()
+			.filter(|&(_, &rank)| rank < 64)
+			.map(|(index, _)| (index as i32 % size, index as i32 / size))
+			.collect();
+
+		let wrap = |offset: i32| offset.rem_euclid(size).min((-offset).rem_euclid(size));
+
+		for (i, a) in kept.iter().enumerate() {
+			for b in &kept[i + 1..] {
+				assert!(wrap(a.0 - b.0).pow(2) + wrap(a.1 - b.1).pow(2) >= 4 * 4, "{a:?} and {b:?} are too close");
+			}
+		}
+	}
+
+	#[test]
+	fn texture_keeps_every_level() {
+		for pattern in DitherPattern::ALL {
+			let mut texels: Vec<u16> = pattern.texture_pixels()
+				.chunks_exact(2)
+				.map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+				.collect();
+
+			let cells = texels.len();
+			texels.sort();
+			texels.dedup();
+			assert_eq!(texels.len(), cells, "{pattern:?} has thresholds that can't be told apart");
+		}
+	}
+}
@@ -0,0 +1,359 @@
+use glam::IVec2;
+use crate::{AtlasTexture, Sprite, Texture, TextureOptions};
+use crate::packing::{RectPacker, SkylinePacker};
+
+
+/// Empty space left around each image, so that linear filtering doesn't sample neighbouring images.
+const PADDING: i32 = 1;
+
+
+/// Something that happened to a `DynamicAtlas` that anything holding on to its texture or sprites needs to know about.
+/// Ordered by severity.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AtlasChange {
+	/// The texture was reallocated at a larger size. Existing sprites are still valid, but anything that cached the
+	/// texture handle or size needs updating - e.g., with `SpriteRenderer::set_texture` or `StaticSpriteBatch::set_texture`.
+	Grown,
+
+	/// Everything was evicted to make room. Existing sprites no longer refer to their images, and must be inserted again.
+	/// The texture may also have been reallocated, as for `Grown`.
+	Evicted,
+}
+
+
+/// Decides where images go in a `DynamicAtlas`, and when it needs to grow or evict. Doesn't touch the GPU.
+///
+/// When an image doesn't fit, the atlas first doubles along its shorter axis until it reaches `max_size`, and then
+/// evicts everything. This suits caches like glyphs, where everything can be regenerated on demand.
+#[derive(Clone, Debug)]
+pub struct AtlasAllocator {
+	packer: SkylinePacker,
+	size: IVec2,
+	max_size: IVec2,
+
+	pending_change: Option<AtlasChange>,
+	/// Bumped by every change, for `generation`.
+	generation: u32,
+}
+
+impl AtlasAllocator {
+	pub fn new(initial_size: IVec2, max_size: IVec2) -> AtlasAllocator {
+		assert!(initial_size.cmpgt(IVec2::ZERO).all() && initial_size.cmple(max_size).all(), "initial size must be positive and no larger than max size");
+
+		AtlasAllocator {
+			// Padding is reserved on the right and top of each image, so allow it to hang off the far edges.
+			packer: SkylinePacker::new(initial_size + PADDING),
+			size: initial_size,
+			max_size,
+
+			pending_change: None,
+			generation: 0,
+		}
+	}
+
+	pub fn size(&self) -> IVec2 {
+		self.size
+	}
+
+	/// Find space for an image of `size`, returning where it was placed and what had to change to make room.
+	/// The change is also remembered for `take_change`.
+	pub fn allocate(&mut self, size: IVec2) -> anyhow::Result<(IVec2, Option<AtlasChange>)> {
+		if size.cmple(IVec2::ZERO).any() || size.cmpgt(self.max_size).any() {
+			anyhow::bail!("Can't allocate {size} in an atlas with a maximum size of {}", self.max_size);
+		}
+
+		let mut change = None;
+
+		loop {
+			if let Some(position) = self.packer.insert(size + PADDING) {
+				if change.is_some() {
+					self.pending_change = self.pending_change.max(change);
+					self.generation = self.generation.wrapping_add(1);
+				}
+
+				return Ok((position, change))
+			}
+
+			if self.size.cmplt(self.max_size).any() {
+				self.grow();
+				change = change.max(Some(AtlasChange::Grown));
+			} else {
+				// Nothing left to try - `size` fits within `max_size`, so it will fit in an empty atlas.
+				assert_ne!(change, Some(AtlasChange::Evicted));
+
+				self.packer.clear();
+				change = Some(AtlasChange::Evicted);
+			}
+		}
+	}
+
+	/// Forget every allocation, without shrinking.
+	pub fn clear(&mut self) {
+		self.packer.clear();
+		self.pending_change = Some(AtlasChange::Evicted);
+		self.generation = self.generation.wrapping_add(1);
+	}
+
+	/// The most severe change since the last call, if any.
+	pub fn take_change(&mut self) -> Option<AtlasChange> {
+		self.pending_change.take()
+	}
+
+	/// Counts changes of any kind. Unlike `take_change`, any number of holders can each remember the generation they
+	/// last saw, and compare it to notice they need updating.
+	pub fn generation(&self) -> u32 {
+		self.generation
+	}
+
+	fn grow(&mut self) {
+		let can_grow_x = self.size.x < self.max_size.x;
+		let can_grow_y = self.size.y < self.max_size.y;
+
+		let grow_x = can_grow_x && (self.size.x <= self.size.y || !can_grow_y);
+
+		self.size = match grow_x {
+			true => IVec2::new((self.size.x * 2).min(self.max_size.x), self.size.y),
+			false => IVec2::new(self.size.x, (self.size.y * 2).min(self.max_size.y)),
+		};
+
+		self.packer.grow(self.size + PADDING);
+	}
+}
+
+
+
+/// An atlas texture that images can be added to at runtime - for glyph caches, user content, and the like.
+///
+/// Inserting may grow the texture or evict everything in it - see `AtlasAllocator`. Nothing drawing from the atlas is
+/// updated automatically: uvs are baked in when sprites are queued or added to a batch, and the texture handle is
+/// cached. After every change - see `take_change` or `generation` - call `set_texture` again on each
+/// `SpriteRenderer` and `StaticSpriteBatch` using the atlas, and re-add any sprites that were evicted. Sprites queued
+/// before the change but not yet drawn should be queued again too, so don't insert between queuing and `draw`.
+pub struct DynamicAtlas {
+	texture: Texture,
+	allocator: AtlasAllocator,
+	options: TextureOptions,
+}
+
+impl DynamicAtlas {
+	/// `options.mipmaps` is ignored, since regenerating them on every insert would be wasteful.
+	pub fn new(initial_size: IVec2, max_size: IVec2, options: &TextureOptions) -> DynamicAtlas {
+		let options = TextureOptions { mipmaps: false, .. *options };
+
+		DynamicAtlas {
+			texture: Texture::empty(initial_size, &options),
+			allocator: AtlasAllocator::new(initial_size, max_size),
+			options,
+		}
+	}
+
+	/// Copy `pixels` into the atlas, returning the sprite that covers them.
+	/// `pixels` is laid out as for `Texture::from_pixels` - bottom row first.
+	pub fn insert(&mut self, size: IVec2, pixels: &[u8]) -> anyhow::Result<Sprite> {
+		// Check before allocating, so a bad upload doesn't leave space reserved.
+		let expected_bytes = size.x.max(0) as usize * size.y.max(0) as usize * self.options.format.bytes_per_pixel();
+		if pixels.len() != expected_bytes {
+			anyhow::bail!("Expected {expected_bytes} bytes of pixel data for a {size} image, but got {}", pixels.len());
+		}
+
+		let (start, change) = self.allocator.allocate(size)?;
+
+		match change {
+			Some(AtlasChange::Grown) => self.reallocate(true),
+			Some(AtlasChange::Evicted) => self.reallocate(false),
+			None => {}
+		}
+
+		self.texture.upload_region(start, size, pixels);
+
+		Ok(Sprite::new(start, size))
+	}
+
+	/// Copy an image into the atlas. Images are stored top row first, so this flips it on the way in.
+	pub fn insert_image(&mut self, image: &image::RgbaImage) -> anyhow::Result<Sprite> {
+		let size = IVec2::new(image.width() as i32, image.height() as i32);
+		let flipped = image::imageops::flip_vertical(image);
+		self.insert(size, flipped.as_raw())
+	}
+
+	/// The most severe change since the last call, if any.
+	pub fn take_change(&mut self) -> Option<AtlasChange> {
+		self.allocator.take_change()
+	}
+
+	/// See `AtlasAllocator::generation`.
+	pub fn generation(&self) -> u32 {
+		self.allocator.generation()
+	}
+
+	/// Evict everything, without shrinking the texture.
+	pub fn clear(&mut self) {
+		self.allocator.clear();
+		self.texture.clear();
+	}
+
+	pub fn texture(&self) -> &Texture {
+		&self.texture
+	}
+
+	/// Replace the texture with one matching the allocator's size, optionally keeping what was already there.
+	fn reallocate(&mut self, keep_contents: bool) {
+		let new_size = self.allocator.size();
+
+		if new_size == self.texture.size() {
+			self.texture.clear();
+			return
+		}
+
+		let new_texture = Texture::empty(new_size, &self.options);
+
+		if keep_contents {
+			let old_size = self.texture.size();
+
+			unsafe {
+				// Sprites are measured from the bottom-left, so copying to the same corner keeps them valid.
+				gl::CopyImageSubData(
+					self.texture.handle(), gl::TEXTURE_2D, 0, 0, 0, 0,
+					new_texture.handle(), gl::TEXTURE_2D, 0, 0, 0, 0,
+					old_size.x, old_size.y, 1
+				);
+			}
+		}
+
+		self.texture = new_texture;
+	}
+}
+
+impl AtlasTexture for DynamicAtlas {
+	fn handle(&self) -> u32 {
+		self.texture.handle()
+	}
+
+	fn size(&self) -> IVec2 {
+		self.texture.size()
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Each 7x7 tile takes 8x8 with padding, so a 16x16 atlas holds exactly 4.
+	const TILE: IVec2 = IVec2::splat(7);
+
+	fn allocate_tiles(allocator: &mut AtlasAllocator, count: usize) -> Vec<(IVec2, Option<AtlasChange>)> {
+		(0..count).map(|_| allocator.allocate(TILE).unwrap()).collect()
+	}
+
+	#[test]
+	fn allocates_until_full() {
+		let mut allocator = AtlasAllocator::new(IVec2::splat(16), IVec2::splat(16));
+
+		let tiles = allocate_tiles(&mut allocator, 4);
+		assert_eq!(tiles, [
+			(IVec2::new(0, 0), None),
+			(IVec2::new(8, 0), None),
+			(IVec2::new(0, 8), None),
+			(IVec2::new(8, 8), None),
+		]);
+
+		assert_eq!(allocator.take_change(), None);
+
+		// Full, and already at the maximum size.
+		assert_eq!(allocator.allocate(TILE).unwrap(), (IVec2::ZERO, Some(AtlasChange::Evicted)));
+		assert_eq!(allocator.take_change(), Some(AtlasChange::Evicted));
+		assert_eq!(allocator.size(), IVec2::splat(16));
+	}
+
+	#[test]
+	fn grows_shorter_axis_before_evicting() {
+		let mut allocator = AtlasAllocator::new(IVec2::splat(16), IVec2::splat(32));
+
+		let tiles = allocate_tiles(&mut allocator, 16);
+		let changes: Vec<usize> = tiles.iter().enumerate()
+			.filter(|(_, (_, change))| change.is_some())
+			.map(|(index, (_, change))| {
+				assert_eq!(*change, Some(AtlasChange::Grown));
+				index
+			})
+			.collect();
+
+		// Wider once the first 4 are in, then taller once there are 8.
+		assert_eq!(changes, [4, 8]);
+		assert_eq!(allocator.size(), IVec2::splat(32));
+		assert_eq!(allocator.take_change(), Some(AtlasChange::Grown));
+		assert_eq!(allocator.take_change(), None);
+
+		// Existing tiles keep their places, and nothing overlaps.
+		assert_eq!(&tiles[..4].iter().map(|&(position, _)| position).collect::<Vec<_>>(), &[
+			IVec2::new(0, 0), IVec2::new(8, 0), IVec2::new(0, 8), IVec2::new(8, 8),
+		]);
+
+		for (index, &(position, _)) in tiles.iter().enumerate() {
+			assert!(position.cmpge(IVec2::ZERO).all() && (position + TILE).cmple(allocator.size()).all());
+			assert!(tiles[..index].iter().all(|&(other, _)| other != position));
+		}
+
+		assert_eq!(allocator.allocate(TILE).unwrap(), (IVec2::ZERO, Some(AtlasChange::Evicted)));
+	}
+
+	#[test]
+	fn reports_most_severe_change() {
+		// Only the top of the atlas can grow, and not by enough for a tall image, so it grows and then evicts.
+		let mut allocator = AtlasAllocator::new(IVec2::splat(16), IVec2::new(16, 32));
+		allocate_tiles(&mut allocator, 4);
+
+		assert_eq!(allocator.allocate(IVec2::new(16, 24)).unwrap(), (IVec2::ZERO, Some(AtlasChange::Evicted)));
+		assert_eq!(allocator.size(), IVec2::new(16, 32));
+
+		// Changes accumulate until taken.
+		allocator.allocate(TILE).unwrap();
+		assert_eq!(allocator.take_change(), Some(AtlasChange::Evicted));
+	}
+
+	#[test]
+	fn reuses_space_after_clear() {
+		let mut allocator = AtlasAllocator::new(IVec2::splat(16), IVec2::splat(32));
+		allocate_tiles(&mut allocator, 6);
+		allocator.take_change();
+
+		allocator.clear();
+		assert_eq!(allocator.take_change(), Some(AtlasChange::Evicted));
+
+		// Cleared space is handed out again from the start, without shrinking or growing.
+		assert_eq!(allocate_tiles(&mut allocator, 2), [(IVec2::new(0, 0), None), (IVec2::new(8, 0), None)]);
+		assert_eq!(allocator.size(), IVec2::new(32, 16));
+		assert_eq!(allocator.take_change(), None);
+	}
+
+	#[test]
+	fn generation_counts_every_change() {
+		let mut allocator = AtlasAllocator::new(IVec2::splat(16), IVec2::new(32, 16));
+		allocate_tiles(&mut allocator, 4);
+		assert_eq!(allocator.generation(), 0);
+
+		// Grows, then evicts - and taking the change doesn't reset the count.
+		allocate_tiles(&mut allocator, 1);
+		assert_eq!(allocator.take_change(), Some(AtlasChange::Grown));
+		allocate_tiles(&mut allocator, 4);
+		assert_eq!(allocator.generation(), 2);
+
+		allocator.clear();
+		assert_eq!(allocator.generation(), 3);
+	}
+
+	#[test]
+	fn rejects_impossible_sizes() {
+		let mut allocator = AtlasAllocator::new(IVec2::splat(16), IVec2::splat(32));
+
+		assert!(allocator.allocate(IVec2::ZERO).is_err());
+		assert!(allocator.allocate(IVec2::new(4, -1)).is_err());
+		assert!(allocator.allocate(IVec2::new(33, 4)).is_err());
+
+		// Nothing was reserved or changed by the failures.
+		assert_eq!(allocator.take_change(), None);
+		assert_eq!(allocator.allocate(TILE).unwrap(), (IVec2::ZERO, None));
+	}
+}
@@ -0,0 +1,151 @@
+mod animation;
+pub mod aseprite;
+mod backend;
+mod billboard;
+mod blend;
+mod dither;
+mod dynamic_atlas;
+pub mod mesh;
+mod nine_slice;
+pub mod packing;
+mod sort_key;
+mod sprite_grid;
+mod sprite_renderer;
+mod sprite_sheet;
+mod static_sprite_batch;
+mod texture;
+mod transform;
+
+pub use animation::{SpriteAnimation, AnimationFrame, AnimationPlayer};
+pub use backend::{SpriteBackend, SpriteInstance, VertexBackend, StorageBufferBackend, InstancedBackend, PointBackend};
+pub use billboard::{Billboard, BillboardSize};
+pub use blend::{BlendMode, SpriteShaders};
+pub use dither::{Dither, DitherPattern, DitherSpace};
+pub use dynamic_atlas::{DynamicAtlas, AtlasAllocator, AtlasChange};
+pub use mesh::{Mesh, MeshBuilder, Vertex, VertexAttribute};
+pub use nine_slice::{NineSlice, SliceFill, SlicePatch};
+pub use sort_key::SortKey;
+pub use sprite_grid::{SpriteGrid, GridOrigin};
+pub use sprite_renderer::{SpriteRenderer, DrawStats, Sprite, Anchor, SpriteVertex, TexturedVertex, CompactTexturedVertex};
+pub use sprite_sheet::{SpriteSheet, SpriteFrame, FrameTag, TagDirection, Slice, SliceKey};
+pub use static_sprite_batch::{StaticSpriteBatch, StaticSpriteId};
+pub use texture::{Texture, ArrayTexture, AtlasTexture, TextureOptions, TextureFormat, TextureFilter, TextureWrap};
+pub use transform::{Transform2D, Transform3D, SpritePlane};
+
+
+
+// ANCHOR: compile_shader
+pub fn compile_shader(ty: u32, src: &str) -> anyhow::Result<u32> {
+	let src_c = std::ffi::CString::new(src)?;
+
+	unsafe {
+		let shader = gl::CreateShader(ty);
+		gl::ShaderSource(shader, 1, &src_c.as_ptr(), std::ptr::null());
+		gl::CompileShader(shader);
+
+		// NOTE: on error, this will technically leak `shader`.
+		// But we don't care for now since we're planning to
+		// bail immediately on error
+		check_shader_status(shader)?;
+
+		Ok(shader)
+	}
+}
+
+
+fn check_shader_status(shader_handle: u32) -> anyhow::Result<()> {
+	unsafe {
+		let mut status = 0;
+		gl::GetShaderiv(shader_handle, gl::COMPILE_STATUS, &mut status);
+
+		if status == 0 {
+			let mut length = 0;
+			gl::GetShaderiv(shader_handle, gl::INFO_LOG_LENGTH, &mut length);
+
+			let mut buffer = vec![0u8; length as usize];
+			gl::GetShaderInfoLog(
+				shader_handle,
+				length,
+				std::ptr::null_mut(),
+				buffer.as_mut_ptr() as *mut _
+			);
+
+			let error_msg = String::from_utf8_lossy(&buffer[..buffer.len()-1]);
+			anyhow::bail!("Shader failed to compile: {error_msg}");
+		}	
+	}
+
+	Ok(())
+}
+// ANCHOR_END: compile_shader
+
+
+/// Compile a shader with `#define`s for each of `defines` inserted after the `#version` directive,
+/// so that a single source file can be compiled into several variants.
+pub fn compile_shader_with_defines(ty: u32, src: &str, defines: &[&str]) -> anyhow::Result<u32> {
+	if defines.is_empty() {
+		return compile_shader(ty, src)
+	}
+
+	// `#version` must come before anything else, so the defines have to go after it.
+	let (version, body, body_line) = match src.split_once('\n') {
+		Some((version, body)) if version.starts_with("#version") => (version, body, 2),
+		_ => ("", src, 1),
+	};
+
+	let defines: String = defines.iter()
+		.map(|define| format!("#define {define}\n"))
+		.collect();
+
+	// `#line` keeps line numbers in error messages matching the original source.
+	compile_shader(ty, &format!("{version}\n{defines}#line {body_line}\n{body}"))
+}
+
+
+// ANCHOR: check_program_status
+pub fn check_program_status(program_handle: u32) -> anyhow::Result<()> {
+	unsafe {
+		let mut status = 0;
+		gl::GetProgramiv(program_handle, gl::LINK_STATUS, &mut status);
+
+		if status == 0 {
+			let mut length = 0;
+			gl::GetProgramiv(program_handle, gl::INFO_LOG_LENGTH, &mut length);
+
+			let mut buffer = vec![0u8; length as usize];
+			gl::GetProgramInfoLog(
+				program_handle,
+				length,
+				std::ptr::null_mut(),
+				buffer.as_mut_ptr() as *mut _
+			);
+
+			let error_msg = String::from_utf8_lossy(&buffer[..buffer.len()-1]);
+			anyhow::bail!("Program failed to link: {error_msg}");
+		}	
+	}
+
+	Ok(())
+}
+// ANCHOR_END: check_program_status
+
+
+
+/// # Safety
+/// A GL context must be current, and `handle` must be a valid buffer.
+pub unsafe fn upload_buffer<T: Copy>(handle: u32, data: &[T], usage: u32) {
+	if data.is_empty() {
+		return
+	}
+
+	let size_bytes = std::mem::size_of_val(data);
+
+	unsafe {
+		gl::NamedBufferData(
+			handle,
+			size_bytes as _,
+			data.as_ptr() as *const _,
+			usage
+		);
+	}
+}
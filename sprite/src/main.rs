@@ -1,5 +1,6 @@
 use anyhow::Error;
-use glam::{Vec2, Vec3, Mat3, Vec4, Mat4, Mat3A, IVec2};
+use glam::{Vec2, Vec3, Vec4, Mat4};
+use sprite::{SpriteRenderer, StaticSpriteBatch, Billboard, BillboardSize, Transform3D, SpritePlane, Anchor, BlendMode, Dither, DitherPattern, DitherSpace, SpriteShaders, VertexBackend, upload_buffer, Texture, TextureOptions};
 
 
 fn main() -> anyhow::Result<()> {
@@ -61,35 +62,6 @@ fn main() -> anyhow::Result<()> {
 	}
 
 
-	// Create a shader program.
-	// ANCHOR: full_shader_process
-	let main_shader = unsafe {
-		let shaders = [
-			compile_shader(gl::VERTEX_SHADER, include_str!("shaders/vert.glsl"))?,
-			compile_shader(gl::FRAGMENT_SHADER, include_str!("shaders/frag.glsl"))?,
-		];
-
-		let program = gl::CreateProgram();
-		for shader in shaders {
-			gl::AttachShader(program, shader);
-		}
-
-		gl::LinkProgram(program);
-
-		// Clean up the shaders we compiled above since we no longer need
-		// them after linking, even if linking failed.
-		for shader in shaders {
-			gl::DetachShader(program, shader);
-			gl::DeleteShader(shader);
-		}
-
-		check_program_status(program)?;
-
-		program
-	};
-	// ANCHOR_END: full_shader_process
-
-
 	// Create a buffer to house our uniforms.
 	let uniform_buffer = unsafe {
 		let mut handle = 0;
@@ -98,10 +70,33 @@ fn main() -> anyhow::Result<()> {
 	};
 
 
-	// Load our sprite atlas.
-	let texture = load_texture("sprite/assets/atlas.png")?;
+	// Load our sprite atlas. Regenerate it from sprite/assets/sprites with
+	// `cargo run -p atlas_packer -- sprite/assets/sprites sprite/assets/atlas --power-of-two`
+	let sheet = sprite::aseprite::load_json("sprite/assets/atlas.json")?;
+	let image_path = sheet.image_path.as_ref().ok_or_else(|| Error::msg("atlas.json doesn't name its image"))?;
+	let texture = Texture::load(image_path, &TextureOptions::default())?;
+
+	let sprite = |name: &str| sheet.sprite(name).copied().ok_or_else(|| anyhow::anyhow!("atlas has no sprite '{name}'"));
+
+	// `SpriteRenderer::<StorageBufferBackend>::with_backend` would pull sprites from a storage buffer instead.
+	// The program bound while drawing it must then be linked with its `VERTEX_SHADER` rather than vert.glsl.
+	let mut sprite_renderer = SpriteRenderer::new(&texture);
+
+	// Our sprites are 64 pixels across, so this draws them a unit wide.
+	const PIXELS_PER_UNIT: f32 = 64.0;
+	sprite_renderer.set_pixels_per_unit(PIXELS_PER_UNIT);
 
-	let mut sprite_renderer = SpriteRenderer::new(IVec2::splat(128));
+	// One program per blend mode, which the renderer switches between as it draws.
+	let sprite_shaders = SpriteShaders::new::<VertexBackend>()?;
+	sprite_renderer.set_shaders(&sprite_shaders);
+
+	// The floor never changes, so bake it once instead of rebuilding it every frame.
+	let mut static_batch = StaticSpriteBatch::new(&texture);
+	static_batch.set_shaders(&sprite_shaders);
+	let squiggle_sprite = sprite("squiggle")?;
+	let guy_sprite = sprite("guy")?;
+	let floor_transform = Transform3D::new(-0.5 * Vec3::Y).with_plane(SpritePlane::XZ);
+	static_batch.quad_raw(&squiggle_sprite, &floor_transform.to_mat3(&squiggle_sprite, PIXELS_PER_UNIT, &Mat4::IDENTITY), Vec4::ONE);
 
 
 	let mut event_pump = sdl_ctx.event_pump()
@@ -211,21 +206,26 @@ fn main() -> anyhow::Result<()> {
 
 			// Bind our uniform buffer to 0th ubo binding slot - matching the layout specified in vert.glsl
 			gl::BindBufferBase(gl::UNIFORM_BUFFER, 0, uniform_buffer);
-
-			// Bind our sprite atlas to 0th texture unit - matching the binding specified in frag.glsl
-			gl::BindTextureUnit(0, texture);
-
-			gl::UseProgram(main_shader);
 		}
 
-		let guy_sprite = Sprite::new(IVec2::new(0, 0), IVec2::splat(64));
-		let squiggle_sprite = Sprite::new(IVec2::new(64, 0), IVec2::splat(64));
+		let guy_sprite = guy_sprite.with_anchor(Anchor::BottomCenter);
 
-		let guy_sprite = Sprite { anchor_2x: IVec2::new(64, 0), .. guy_sprite };
+		// Fade in and out, so the dithering in frag.glsl has something to do.
+		let guy_color = Vec4::new(1.0, 1.0, 1.0, (time*0.8).cos() * 0.4 + 0.6);
 
-		sprite_renderer.quad_raw(&guy_sprite, &Mat3::from_cols(Vec3::X, Vec3::Y, -0.5 * Vec3::Y));
-		sprite_renderer.quad_raw(&squiggle_sprite, &Mat3::from_cols(Vec3::X, -Vec3::Z, -0.5 * Vec3::Y));
+		// Anchored to the sprite's texels, so the pattern doesn't crawl across it as the camera moves.
+		sprite_renderer.set_blend_mode(BlendMode::Dithered);
+		sprite_renderer.set_dither(Dither::new(DitherPattern::Bayer4x4).with_space(DitherSpace::Object));
+		sprite_renderer.place(&guy_sprite, Transform3D::new(-0.5 * Vec3::Y), guy_color);
 
+		// A second, glowing guy circling the first, always turned towards the camera.
+		let orbit = Vec2::from((0.6 * time).sin_cos()) * 1.5;
+		sprite_renderer.set_view(&view_matrix);
+		sprite_renderer.set_blend_mode(BlendMode::Additive);
+		sprite_renderer.billboard(&guy_sprite, Billboard::Cylindrical, Vec3::new(orbit.x, -0.5, orbit.y), BillboardSize::World(0.6), Vec4::new(0.4, 0.7, 1.0, 1.0));
+
+		// The floor goes first, so that blended sprites have something to blend with.
+		static_batch.draw();
 		sprite_renderer.draw();
 
 
@@ -288,99 +288,6 @@ extern "system" fn gl_message_callback(source: u32, ty: u32, _id: u32, severity:
 }
 
 
-// ANCHOR: compile_shader
-fn compile_shader(ty: u32, src: &str) -> anyhow::Result<u32> {
-	let src_c = std::ffi::CString::new(src)?;
-
-	unsafe {
-		let shader = gl::CreateShader(ty);
-		gl::ShaderSource(shader, 1, &src_c.as_ptr(), std::ptr::null());
-		gl::CompileShader(shader);
-
-		// NOTE: on error, this will technically leak `shader`.
-		// But we don't care for now since we're planning to
-		// bail immediately on error
-		check_shader_status(shader)?;
-
-		Ok(shader)
-	}
-}
-
-
-fn check_shader_status(shader_handle: u32) -> anyhow::Result<()> {
-	unsafe {
-		let mut status = 0;
-		gl::GetShaderiv(shader_handle, gl::COMPILE_STATUS, &mut status);
-
-		if status == 0 {
-			let mut length = 0;
-			gl::GetShaderiv(shader_handle, gl::INFO_LOG_LENGTH, &mut length);
-
-			let mut buffer = vec![0u8; length as usize];
-			gl::GetShaderInfoLog(
-				shader_handle,
-				length,
-				std::ptr::null_mut(),
-				buffer.as_mut_ptr() as *mut _
-			);
-
-			let error_msg = String::from_utf8_lossy(&buffer[..buffer.len()-1]);
-			anyhow::bail!("Shader failed to compile: {error_msg}");
-		}	
-	}
-
-	Ok(())
-}
-// ANCHOR_END: compile_shader
-
-
-// ANCHOR: check_program_status
-fn check_program_status(program_handle: u32) -> anyhow::Result<()> {
-	unsafe {
-		let mut status = 0;
-		gl::GetProgramiv(program_handle, gl::LINK_STATUS, &mut status);
-
-		if status == 0 {
-			let mut length = 0;
-			gl::GetProgramiv(program_handle, gl::INFO_LOG_LENGTH, &mut length);
-
-			let mut buffer = vec![0u8; length as usize];
-			gl::GetProgramInfoLog(
-				program_handle,
-				length,
-				std::ptr::null_mut(),
-				buffer.as_mut_ptr() as *mut _
-			);
-
-			let error_msg = String::from_utf8_lossy(&buffer[..buffer.len()-1]);
-			anyhow::bail!("Program failed to link: {error_msg}");
-		}	
-	}
-
-	Ok(())
-}
-// ANCHOR_END: check_program_status
-
-
-
-unsafe fn upload_buffer<T: Copy>(handle: u32, data: &[T], usage: u32) {
-	if data.is_empty() {
-		return
-	}
-
-	let size_bytes = data.len() * std::mem::size_of::<T>();
-
-	unsafe {
-		gl::NamedBufferData(
-			handle,
-			size_bytes as _,
-			data.as_ptr() as *const _,
-			usage
-		);
-	}
-}
-
-
 
 // NOTE: Must respect glsl std140 layout rules.
 // Lucky for us, Mat4 fits this description.
@@ -389,183 +296,3 @@ unsafe fn upload_buffer<T: Copy>(handle: u32, data: &[T], usage: u32) {
 struct Uniforms {
 	projection: Mat4,
 }
-
-
-
-pub fn load_texture(path: impl AsRef<std::path::Path>) -> anyhow::Result<u32> {
-	let image = image::open(path)?.flipv().into_rgba8().into_flat_samples();
-	let (width, height) = (image.layout.width as i32, image.layout.height as i32);
-	let data = image.samples;
-
-	unsafe {
-		let mut texture_handle = 0;
-		gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture_handle);
-
-		// Allocate storage
-		gl::TextureStorage2D(texture_handle, 1, gl::SRGB8_ALPHA8, width, height);
-
-		// Upload image data
-		let (level, offset_x, offset_y) = (0, 0, 0);
-		gl::TextureSubImage2D(
-			texture_handle,
-			level, offset_x, offset_y,
-			width, height,
-			gl::RGBA,
-			gl::UNSIGNED_BYTE,
-			data.as_ptr() as *const _
-		);
-
-		// Set sampling parameters.
-		// If we don't set these we'd need to generate mipmaps, since GL_TEXTURE_MIN_FILTER defaults to GL_NEAREST_MIPMAP_LINEAR
-		gl::TextureParameteri(texture_handle, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-		gl::TextureParameteri(texture_handle, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-
-		Ok(texture_handle)
-	}
-}
-
-
-#[repr(C)]
-#[derive(Copy, Clone)]
-pub struct TexturedVertex {
-	position: Vec3,
-	uv: Vec2,
-}
-
-
-
-pub struct SpriteRenderer {
-	vertices: Vec<TexturedVertex>,
-	indices: Vec<u16>,
-
-	texture_size: IVec2,
-
-	vao: u32,
-	vertex_buffer: u32,
-	index_buffer: u32,
-}
-
-impl SpriteRenderer {
-	pub fn new(texture_size: IVec2) -> SpriteRenderer {
-		let mut vao = 0;
-		let mut vertex_buffer = 0;
-		let mut index_buffer = 0;
-
-		let vertex_stride = std::mem::size_of::<TexturedVertex>() as i32;
-		let vertex_bind_index = 0;
-
-		unsafe {
-			gl::CreateVertexArrays(1, &mut vao);
-			gl::CreateBuffers(1, &mut vertex_buffer);
-			gl::CreateBuffers(1, &mut index_buffer);
-
-			// Bind vertex buffer to vao and describe which attributes should pull from it
-			gl::VertexArrayVertexBuffer(vao, vertex_bind_index, vertex_buffer, 0, vertex_stride);
-			gl::VertexArrayAttribBinding(vao, 0 /* attribute index */, vertex_bind_index);
-			gl::VertexArrayAttribBinding(vao, 1 /* attribute index */, vertex_bind_index);
-
-			// Bind index buffer to vao
-			gl::VertexArrayElementBuffer(vao, index_buffer);
-
-			// Describe layout of attributes in vertex buffer
-			gl::VertexArrayAttribFormat(
-				vao,
-				0, // attribute index
-				3, // num elements
-				gl::FLOAT,
-				gl::FALSE, // normalised?
-				0, // offset in bytes into struct
-			);
-			gl::VertexArrayAttribFormat(
-				vao,
-				1, // attribute index
-				2, // num elements
-				gl::FLOAT,
-				gl::FALSE, // normalised?
-				std::mem::size_of::<Vec3>() as _, // offset in bytes into struct
-			);
-
-			// Enable pulling vertex attributes from vertex buffer
-			gl::EnableVertexArrayAttrib(vao, 0);
-			gl::EnableVertexArrayAttrib(vao, 1);
-		}
-
-		SpriteRenderer {
-			vertices: Vec::new(),
-			indices: Vec::new(),
-			texture_size,
-			vao, vertex_buffer, index_buffer,
-		}
-	}
-
-	pub fn draw(&mut self) {
-		let element_count = self.indices.len() as i32;
-		let draw_offset = std::ptr::null();
-
-		// Update buffers
-		unsafe {
-			upload_buffer(self.vertex_buffer, &self.vertices, gl::STREAM_DRAW);
-			upload_buffer(self.index_buffer, &self.indices, gl::STREAM_DRAW);
-		}
-
-		// Clear for next frame.
-		self.vertices.clear();
-		self.indices.clear();
-
-		// Draw
-		unsafe {
-			gl::BindVertexArray(self.vao);
-			gl::DrawElements(gl::TRIANGLES, element_count, gl::UNSIGNED_SHORT, draw_offset);
-		}
-	}
-
-	pub fn quad_raw(&mut self, sprite: &Sprite, transform: &Mat3) {
-		let positions = [
-			Vec2::new(0.0,0.0),
-			Vec2::new(0.0,1.0),
-			Vec2::new(1.0,1.0),
-			Vec2::new(1.0,0.0),
-		];
-
-		let vertex_start = self.vertices.len() as u16;
-		let uv_scale = sprite.size.as_vec2() / self.texture_size.as_vec2();
-		let uv_offset = sprite.start.as_vec2() / self.texture_size.as_vec2();
-		let anchor_offset = sprite.anchor_2x.as_vec2() / sprite.size.as_vec2() / 2.0;
-
-		let new_vertices = positions.into_iter()
-			.map(|pos2| {
-				let position = *transform * (pos2 - anchor_offset).extend(1.0);
-				let uv = pos2 * uv_scale + uv_offset;
-
-				TexturedVertex {position, uv}
-			});
-
-		let new_indices = [
-			vertex_start + 0,
-			vertex_start + 1,
-			vertex_start + 2,
-			vertex_start + 0,
-			vertex_start + 2,
-			vertex_start + 3,
-		];
-
-		self.vertices.extend(new_vertices);
-		self.indices.extend(new_indices);
-	}
-}
-
-pub struct Sprite {
-	pub start: IVec2,
-	pub size: IVec2,
-	pub anchor_2x: IVec2,
-}
-
-impl Sprite {
-	pub const fn new(start: IVec2, size: IVec2) -> Sprite {
-		Sprite {
-			start,
-			size,
-			anchor_2x: size,
-		}
-	}
-}
\ No newline at end of file
@@ -0,0 +1,526 @@
+use glam::{Vec2, Vec3, Mat4, IVec2};
+use std::marker::PhantomData;
+use std::ops::Range;
+use crate::upload_buffer;
+
+
+/// Describes a single attribute within a vertex, in the terms `glVertexArrayAttribFormat` wants.
+#[derive(Copy, Clone, Debug)]
+pub struct VertexAttribute {
+	pub num_elements: i32,
+	pub component_type: u32,
+	pub normalized: bool,
+	pub offset_bytes: u32,
+}
+
+impl VertexAttribute {
+	pub const fn new(num_elements: i32, component_type: u32, offset_bytes: usize) -> VertexAttribute {
+		VertexAttribute {
+			num_elements,
+			component_type,
+			normalized: false,
+			offset_bytes: offset_bytes as u32,
+		}
+	}
+
+	/// An integer attribute that should be remapped to the 0..1 (or -1..1 if signed) range when read by the shader.
+	pub const fn normalized(num_elements: i32, component_type: u32, offset_bytes: usize) -> VertexAttribute {
+		VertexAttribute {
+			normalized: true,
+			.. VertexAttribute::new(num_elements, component_type, offset_bytes)
+		}
+	}
+}
+
+
+/// A vertex type that can be stored in a vertex buffer and generated by a `MeshBuilder`.
+/// Attribute `i` in `ATTRIBUTES` is bound to `layout(location=i)` in the vertex shader.
+pub trait Vertex: Copy {
+	const ATTRIBUTES: &'static [VertexAttribute];
+
+	fn new(position: Vec3, uv: Vec2) -> Self;
+}
+
+
+// ANCHOR: configure_vertex_array
+/// Bind `vertex_buffer` and `index_buffer` to `vao` and describe the layout of `V` to it.
+///
+/// # Safety
+/// A GL context must be current, and all handles must be valid.
+pub unsafe fn configure_vertex_array<V: Vertex>(vao: u32, vertex_buffer: u32, index_buffer: u32) {
+	let vertex_stride = std::mem::size_of::<V>() as i32;
+	let vertex_bind_index = 0;
+
+	unsafe {
+		// Bind vertex buffer to vao and describe which attributes should pull from it
+		gl::VertexArrayVertexBuffer(vao, vertex_bind_index, vertex_buffer, 0, vertex_stride);
+
+		// Bind index buffer to vao
+		gl::VertexArrayElementBuffer(vao, index_buffer);
+
+		for (attribute_index, attribute) in V::ATTRIBUTES.iter().enumerate() {
+			let attribute_index = attribute_index as u32;
+
+			gl::VertexArrayAttribBinding(vao, attribute_index, vertex_bind_index);
+
+			// Describe layout of attribute in vertex buffer
+			gl::VertexArrayAttribFormat(
+				vao,
+				attribute_index,
+				attribute.num_elements,
+				attribute.component_type,
+				attribute.normalized as u8,
+				attribute.offset_bytes,
+			);
+
+			// Enable pulling vertex attribute from vertex buffer
+			gl::EnableVertexArrayAttrib(vao, attribute_index);
+		}
+	}
+}
+// ANCHOR_END: configure_vertex_array
+
+
+
+/// Accumulates indexed geometry on the CPU, ready to be uploaded to a `Mesh`.
+///
+/// Geometry generated by the primitive functions is transformed by the current transform as it is appended,
+/// so the same primitive can be placed multiple times without any extra bookkeeping.
+pub struct MeshBuilder<V: Vertex> {
+	pub vertices: Vec<V>,
+	pub indices: Vec<u16>,
+
+	transform: Mat4,
+}
+
+impl<V: Vertex> Default for MeshBuilder<V> {
+	fn default() -> Self {
+		MeshBuilder::new()
+	}
+}
+
+impl<V: Vertex> MeshBuilder<V> {
+	/// The most vertices a builder can hold - one for every u16 index, 0 to 65535.
+	pub const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+	pub fn new() -> MeshBuilder<V> {
+		MeshBuilder {
+			vertices: Vec::new(),
+			indices: Vec::new(),
+			transform: Mat4::IDENTITY,
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.vertices.clear();
+		self.indices.clear();
+	}
+
+	pub fn set_transform(&mut self, transform: Mat4) {
+		self.transform = transform;
+	}
+
+	pub fn transform(&self) -> Mat4 {
+		self.transform
+	}
+
+	// ANCHOR: append
+	/// Append vertices as-is, ignoring the current transform.
+	/// `indices` are relative to the first vertex passed in.
+	///
+	/// Panics, leaving the builder unchanged, if there would be more vertices than u16 indices can address.
+	pub fn append(&mut self, vertices: impl IntoIterator<Item=V>, indices: impl IntoIterator<Item=u16>) {
+		let vertex_start = self.vertices.len();
+		self.vertices.extend(vertices);
+
+		// Make sure we haven't run out of indices, before offsetting any.
+		if self.vertices.len() > Self::MAX_VERTICES {
+			self.vertices.truncate(vertex_start);
+			panic!("MeshBuilder exceeded the number of vertices addressable by u16 indices");
+		}
+
+		let index_start = self.indices.len();
+		for index in indices {
+			let Some(offset_index) = u16::try_from(vertex_start).ok().and_then(|start| start.checked_add(index)) else {
+				self.vertices.truncate(vertex_start);
+				self.indices.truncate(index_start);
+				panic!("MeshBuilder index {index} is past the last vertex addressable by u16 indices once offset by {vertex_start}");
+			};
+
+			self.indices.push(offset_index);
+		}
+	}
+
+	/// Append positions and uvs, transforming positions by the current transform.
+	/// `indices` are relative to the first vertex passed in.
+	pub fn append_transformed(&mut self, vertices: impl IntoIterator<Item=(Vec3, Vec2)>, indices: impl IntoIterator<Item=u16>) {
+		let transform = self.transform;
+		let vertices = vertices.into_iter()
+			.map(|(position, uv)| V::new(transform.transform_point3(position), uv));
+
+		self.append(vertices, indices);
+	}
+	// ANCHOR_END: append
+
+	// ANCHOR: quad
+	/// A quad on the XY plane centered on the origin, facing +Z.
+	pub fn quad(&mut self, size: Vec2) {
+		let vertices = [
+			Vec2::new(0.0, 0.0),
+			Vec2::new(1.0, 0.0),
+			Vec2::new(1.0, 1.0),
+			Vec2::new(0.0, 1.0),
+		].map(|uv| (((uv - 0.5) * size).extend(0.0), uv));
+
+		self.append_transformed(vertices, [0, 1, 2, 0, 2, 3]);
+	}
+	// ANCHOR_END: quad
+
+	/// A grid of `cells.x * cells.y` quads on the XY plane centered on the origin, facing +Z.
+	/// Vertices are shared between neighbouring cells, and uvs span the whole grid.
+	pub fn grid(&mut self, cells: IVec2, size: Vec2) {
+		assert!(cells.x > 0 && cells.y > 0, "grid must have at least one cell");
+
+		let row_length = cells.x as u16 + 1;
+
+		let vertices = (0..=cells.y)
+			.flat_map(|y| (0..=cells.x).map(move |x| IVec2::new(x, y)))
+			.map(|cell| {
+				let uv = cell.as_vec2() / cells.as_vec2();
+				(((uv - 0.5) * size).extend(0.0), uv)
+			});
+
+		let indices = (0..cells.y as u16)
+			.flat_map(|y| (0..cells.x as u16).map(move |x| y * row_length + x))
+			.flat_map(|start| [
+				start,
+				start + 1,
+				start + row_length + 1,
+				start,
+				start + row_length + 1,
+				start + row_length,
+			]);
+
+		self.append_transformed(vertices, indices);
+	}
+
+	/// A circle on the XY plane centered on the origin, facing +Z.
+	/// Uvs map the circle into the unit square.
+	pub fn circle(&mut self, radius: f32, segments: u16) {
+		assert!(segments >= 3, "circle must have at least 3 segments");
+
+		let center = (Vec3::ZERO, Vec2::splat(0.5));
+		let rim = (0..segments)
+			.map(|segment| {
+				let angle = segment as f32 / segments as f32 * std::f32::consts::TAU;
+				let direction = Vec2::new(angle.cos(), angle.sin());
+				((direction * radius).extend(0.0), direction * 0.5 + 0.5)
+			});
+
+		let indices = (0..segments)
+			.flat_map(|segment| [0, segment + 1, (segment + 1) % segments + 1]);
+
+		self.append_transformed(std::iter::once(center).chain(rim), indices);
+	}
+
+	/// An axis aligned box centered on the origin, with outward facing faces.
+	/// Each face has its own vertices so that uvs span the full 0..1 range per face.
+	pub fn cube(&mut self, size: Vec3) {
+		// Each face is described by its normal, and the directions uv.x and uv.y increase along.
+		let faces = [
+			(Vec3::X, -Vec3::Z, Vec3::Y),
+			(-Vec3::X, Vec3::Z, Vec3::Y),
+			(Vec3::Y, Vec3::X, -Vec3::Z),
+			(-Vec3::Y, Vec3::X, Vec3::Z),
+			(Vec3::Z, Vec3::X, Vec3::Y),
+			(-Vec3::Z, -Vec3::X, Vec3::Y),
+		];
+
+		for (normal, right, up) in faces {
+			let vertices = [
+				Vec2::new(0.0, 0.0),
+				Vec2::new(1.0, 0.0),
+				Vec2::new(1.0, 1.0),
+				Vec2::new(0.0, 1.0),
+			].map(|uv| {
+				let local = normal + right * (uv.x * 2.0 - 1.0) + up * (uv.y * 2.0 - 1.0);
+				(local * size * 0.5, uv)
+			});
+
+			self.append_transformed(vertices, [0, 1, 2, 0, 2, 3]);
+		}
+	}
+
+	/// A strip of quads following `points`, extruded `width` wide on the XY plane.
+	/// Uvs run from 0 to 1 along the length of the strip and across its width.
+	pub fn line_strip(&mut self, points: &[Vec3], width: f32) {
+		if points.len() < 2 {
+			return
+		}
+
+		let total_length: f32 = points.windows(2)
+			.map(|pair| pair[0].distance(pair[1]))
+			.sum();
+
+		let mut distance = 0.0;
+
+		for pair in points.windows(2) {
+			let (start, end) = (pair[0], pair[1]);
+			let length = start.distance(end);
+
+			let side = (end - start).truncate().perp().normalize_or_zero().extend(0.0) * width / 2.0;
+			let (u_start, u_end) = (distance / total_length, (distance + length) / total_length);
+
+			let vertices = [
+				(start - side, Vec2::new(u_start, 0.0)),
+				(end - side, Vec2::new(u_end, 0.0)),
+				(end + side, Vec2::new(u_end, 1.0)),
+				(start + side, Vec2::new(u_start, 1.0)),
+			];
+
+			self.append_transformed(vertices, [0, 1, 2, 0, 2, 3]);
+
+			distance += length;
+		}
+	}
+
+}
+
+
+
+/// Geometry that lives on the GPU, along with the vertex array describing it.
+pub struct Mesh<V: Vertex> {
+	vao: u32,
+	vertex_buffer: u32,
+	index_buffer: u32,
+	element_count: i32,
+
+	_vertex: PhantomData<V>,
+}
+
+impl<V: Vertex> Mesh<V> {
+	pub fn new() -> Mesh<V> {
+		let mut vao = 0;
+		let mut vertex_buffer = 0;
+		let mut index_buffer = 0;
+
+		unsafe {
+			gl::CreateVertexArrays(1, &mut vao);
+			gl::CreateBuffers(1, &mut vertex_buffer);
+			gl::CreateBuffers(1, &mut index_buffer);
+
+			configure_vertex_array::<V>(vao, vertex_buffer, index_buffer);
+		}
+
+		Mesh {
+			vao, vertex_buffer, index_buffer,
+			element_count: 0,
+			_vertex: PhantomData,
+		}
+	}
+
+	pub fn from_builder(builder: &MeshBuilder<V>) -> Mesh<V> {
+		let mut mesh = Mesh::new();
+		mesh.upload(builder, gl::STATIC_DRAW);
+		mesh
+	}
+
+	/// Replace the contents of the mesh. `usage` is a hint passed on to `glNamedBufferData`.
+	pub fn upload(&mut self, builder: &MeshBuilder<V>, usage: u32) {
+		unsafe {
+			upload_buffer(self.vertex_buffer, &builder.vertices, usage);
+			upload_buffer(self.index_buffer, &builder.indices, usage);
+		}
+
+		self.element_count = builder.indices.len() as i32;
+	}
+
+	/// Overwrite part of the vertex buffer in place, starting at vertex `first_vertex`.
+	/// The buffer must already be large enough, i.e., this can't be used to grow the mesh.
+	pub fn update_vertices(&mut self, first_vertex: usize, vertices: &[V]) {
+		if vertices.is_empty() {
+			return
+		}
+
+		let offset_bytes = first_vertex * std::mem::size_of::<V>();
+
+		unsafe {
+			gl::NamedBufferSubData(
+				self.vertex_buffer,
+				offset_bytes as _,
+				std::mem::size_of_val(vertices) as _,
+				vertices.as_ptr() as *const _
+			);
+		}
+	}
+
+	pub fn draw(&self) {
+		self.draw_range(0 .. self.element_count as usize);
+	}
+
+	/// Draw only the triangles described by `indices`, a range into the index buffer.
+	pub fn draw_range(&self, indices: Range<usize>) {
+		if indices.is_empty() {
+			return
+		}
+
+		let draw_offset = indices.start * std::mem::size_of::<u16>();
+
+		unsafe {
+			gl::BindVertexArray(self.vao);
+			gl::DrawElements(gl::TRIANGLES, indices.len() as i32, gl::UNSIGNED_SHORT, draw_offset as *const _);
+		}
+	}
+}
+
+impl<V: Vertex> Default for Mesh<V> {
+	fn default() -> Self {
+		Mesh::new()
+	}
+}
+
+impl<V: Vertex> Drop for Mesh<V> {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteVertexArrays(1, &self.vao);
+			gl::DeleteBuffers(1, &self.vertex_buffer);
+			gl::DeleteBuffers(1, &self.index_buffer);
+		}
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Copy, Clone, Debug, PartialEq)]
+	struct TestVertex {
+		position: Vec3,
+		uv: Vec2,
+	}
+
+	impl Vertex for TestVertex {
+		const ATTRIBUTES: &'static [VertexAttribute] = &[];
+
+		fn new(position: Vec3, uv: Vec2) -> TestVertex {
+			TestVertex {position, uv}
+		}
+	}
+
+	fn counts(build: impl FnOnce(&mut MeshBuilder<TestVertex>)) -> (usize, usize) {
+		let mut builder = MeshBuilder::new();
+		build(&mut builder);
+		(builder.vertices.len(), builder.indices.len())
+	}
+
+	#[test]
+	fn shape_counts() {
+		assert_eq!(counts(|builder| builder.quad(Vec2::ONE)), (4, 6));
+		assert_eq!(counts(|builder| builder.grid(IVec2::new(3, 2), Vec2::ONE)), (12, 36));
+		assert_eq!(counts(|builder| builder.circle(1.0, 8)), (9, 24));
+		assert_eq!(counts(|builder| builder.cube(Vec3::ONE)), (24, 36));
+		assert_eq!(counts(|builder| builder.line_strip(&[Vec3::ZERO, Vec3::X, Vec3::ONE], 0.5)), (8, 12));
+		assert_eq!(counts(|builder| builder.line_strip(&[Vec3::ZERO], 0.5)), (0, 0));
+	}
+
+	#[test]
+	fn indices_are_offset_by_existing_vertices() {
+		let mut builder = MeshBuilder::<TestVertex>::new();
+		builder.quad(Vec2::ONE);
+		builder.quad(Vec2::ONE);
+		assert_eq!(builder.indices, [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]);
+
+		let vertex = TestVertex::new(Vec3::ZERO, Vec2::ZERO);
+		builder.append([vertex; 3], [0, 2, 1]);
+		assert_eq!(builder.indices[12..], [8, 10, 9]);
+
+		builder.append_transformed([(Vec3::ZERO, Vec2::ZERO); 3], [2, 1, 0]);
+		assert_eq!(builder.indices[15..], [13, 12, 11]);
+		assert_eq!(builder.vertices.len(), 14);
+	}
+
+	#[test]
+	fn grid_triangles() {
+		let mut builder = MeshBuilder::<TestVertex>::new();
+		builder.grid(IVec2::new(2, 1), Vec2::new(2.0, 1.0));
+
+		// Rows of 3 vertices, bottom row first.
+		assert_eq!(builder.indices, [
+			0, 1, 4, 0, 4, 3,
+			1, 2, 5, 1, 5, 4,
+		]);
+
+		assert_eq!(builder.vertices[0].position, Vec3::new(-1.0, -0.5, 0.0));
+		assert_eq!(builder.vertices[5].position, Vec3::new(1.0, 0.5, 0.0));
+		assert_eq!(builder.vertices[4].uv, Vec2::new(0.5, 1.0));
+	}
+
+	#[test]
+	fn circle_wraps_around() {
+		let mut builder = MeshBuilder::<TestVertex>::new();
+		builder.circle(2.0, 4);
+
+		assert_eq!(builder.indices, [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1]);
+		assert_eq!(builder.vertices[0].position, Vec3::ZERO);
+		assert!(builder.vertices[1].position.abs_diff_eq(Vec3::new(2.0, 0.0, 0.0), 1e-6));
+		assert!(builder.vertices[2].position.abs_diff_eq(Vec3::new(0.0, 2.0, 0.0), 1e-6));
+	}
+
+	#[test]
+	fn transform_applies_to_positions() {
+		let mut builder = MeshBuilder::<TestVertex>::new();
+		builder.set_transform(Mat4::from_translation(Vec3::new(10.0, 0.0, 0.0)) * Mat4::from_scale(Vec3::splat(2.0)));
+		builder.quad(Vec2::ONE);
+
+		let positions: Vec<Vec3> = builder.vertices.iter().map(|vertex| vertex.position).collect();
+		assert_eq!(positions, [
+			Vec3::new(9.0, -1.0, 0.0),
+			Vec3::new(11.0, -1.0, 0.0),
+			Vec3::new(11.0, 1.0, 0.0),
+			Vec3::new(9.0, 1.0, 0.0),
+		]);
+
+		// Uvs are left alone, and `append` ignores the transform entirely.
+		assert_eq!(builder.vertices[2].uv, Vec2::ONE);
+
+		builder.append([TestVertex::new(Vec3::ONE, Vec2::ZERO)], []);
+		assert_eq!(builder.vertices[4].position, Vec3::ONE);
+	}
+
+	#[test]
+	#[should_panic(expected = "u16 indices")]
+	fn too_many_vertices() {
+		let mut builder = MeshBuilder::<TestVertex>::new();
+		let vertex = TestVertex::new(Vec3::ZERO, Vec2::ZERO);
+		builder.append(std::iter::repeat_n(vertex, MeshBuilder::<TestVertex>::MAX_VERTICES + 1), []);
+	}
+
+	#[test]
+	fn fills_every_index() {
+		let mut builder = MeshBuilder::<TestVertex>::new();
+		let vertex = TestVertex::new(Vec3::ZERO, Vec2::ZERO);
+		builder.append(std::iter::repeat_n(vertex, MeshBuilder::<TestVertex>::MAX_VERTICES - 3), []);
+		builder.append([vertex; 3], [0, 1, 2]);
+
+		assert_eq!(builder.vertices.len(), MeshBuilder::<TestVertex>::MAX_VERTICES);
+		assert_eq!(builder.indices, [u16::MAX - 2, u16::MAX - 1, u16::MAX]);
+	}
+
+	#[test]
+	fn overflowing_append_changes_nothing() {
+		let mut builder = MeshBuilder::<TestVertex>::new();
+		let vertex = TestVertex::new(Vec3::ZERO, Vec2::ZERO);
+		builder.append(std::iter::repeat_n(vertex, u16::MAX as usize), [0, 1, 2]);
+
+		// Room for the vertex, but its index would wrap around.
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| builder.append([vertex], [0, 1])));
+		assert!(result.is_err());
+		assert_eq!((builder.vertices.len(), builder.indices.len()), (u16::MAX as usize, 3));
+
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| builder.append([vertex; 2], [])));
+		assert!(result.is_err());
+		assert_eq!((builder.vertices.len(), builder.indices.len()), (u16::MAX as usize, 3));
+	}
+}
@@ -0,0 +1,349 @@
+use glam::{Vec2, Vec4, Mat3, IVec2};
+use crate::Sprite;
+use crate::backend::SpriteInstance;
+
+
+/// How the stretchable parts of a `NineSlice` fill their space.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SliceFill {
+	/// Scale the source to fit. Always a single quad.
+	Stretch,
+
+	/// Repeat the source at its native size, cropping the last repetition. One quad per repetition.
+	Tile,
+}
+
+
+/// A sprite split into a 3x3 grid by border insets, so it can be drawn at any size without distorting its corners.
+///
+/// Corners are always drawn at their native size, edges stretch or tile along one axis, and the center along both.
+/// Leaving the insets on one axis at zero gives a three-slice.
+#[derive(Copy, Clone, Debug)]
+pub struct NineSlice {
+	pub sprite: Sprite,
+
+	/// Border widths at the left and bottom, in pixels.
+	pub border_min: IVec2,
+
+	/// Border widths at the right and top, in pixels.
+	pub border_max: IVec2,
+
+	pub edge_fill: SliceFill,
+	pub center_fill: SliceFill,
+}
+
+/// One quad of a `NineSlice` drawn at some size. All values in pixels, measured from the bottom-left.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SlicePatch {
+	/// Where the patch goes within the target rectangle.
+	pub start: Vec2,
+	pub size: Vec2,
+
+	/// Which part of the sprite it shows, relative to `sprite.start`.
+	pub source_start: Vec2,
+	pub source_size: Vec2,
+}
+
+
+impl NineSlice {
+	pub fn new(sprite: Sprite, border_min: IVec2, border_max: IVec2) -> NineSlice {
+		assert!((border_min + border_max).cmple(sprite.size).all(), "nine slice borders are larger than the sprite");
+
+		NineSlice {
+			sprite,
+			border_min,
+			border_max,
+			edge_fill: SliceFill::Stretch,
+			center_fill: SliceFill::Stretch,
+		}
+	}
+
+	/// The same border width on all sides.
+	pub fn uniform(sprite: Sprite, border: i32) -> NineSlice {
+		NineSlice::new(sprite, IVec2::splat(border), IVec2::splat(border))
+	}
+
+	/// A three-slice that only stretches horizontally, e.g., for buttons and bars.
+	pub fn horizontal(sprite: Sprite, left: i32, right: i32) -> NineSlice {
+		NineSlice::new(sprite, IVec2::new(left, 0), IVec2::new(right, 0))
+	}
+
+	/// A three-slice that only stretches vertically.
+	pub fn vertical(sprite: Sprite, bottom: i32, top: i32) -> NineSlice {
+		NineSlice::new(sprite, IVec2::new(0, bottom), IVec2::new(0, top))
+	}
+
+	/// From the stretchable center of the sprite, in pixels from its top-left - as in `SliceKey::center`.
+	pub fn from_center(sprite: Sprite, center_start: IVec2, center_size: IVec2) -> NineSlice {
+		let left_top = center_start;
+		let right_bottom = sprite.size - center_start - center_size;
+
+		NineSlice::new(sprite, IVec2::new(left_top.x, right_bottom.y), IVec2::new(right_bottom.x, left_top.y))
+	}
+
+	pub fn with_edge_fill(mut self, fill: SliceFill) -> NineSlice {
+		self.edge_fill = fill;
+		self
+	}
+
+	pub fn with_center_fill(mut self, fill: SliceFill) -> NineSlice {
+		self.center_fill = fill;
+		self
+	}
+
+	/// Split a rectangle of `size` pixels into the quads needed to draw this slice.
+	///
+	/// If `size` is smaller than the borders, they are scaled down to fit and the middle is dropped.
+	/// Empty patches are skipped, so a three-slice produces at most three quads when stretched.
+	pub fn patches(&self, size: Vec2) -> Vec<SlicePatch> {
+		let parts = [SlicePart::Min, SlicePart::Middle, SlicePart::Max];
+		let mut patches = Vec::new();
+
+		for y_part in parts {
+			for x_part in parts {
+				let fill = match (x_part, y_part) {
+					(SlicePart::Middle, SlicePart::Middle) => self.center_fill,
+					_ => self.edge_fill,
+				};
+
+				let x_segments = self.axis_segments(0, x_part, size.x, fill);
+				let y_segments = self.axis_segments(1, y_part, size.y, fill);
+
+				for y in &y_segments {
+					for x in &x_segments {
+						patches.push(SlicePatch {
+							start: Vec2::new(x.start, y.start),
+							size: Vec2::new(x.size, y.size),
+							source_start: Vec2::new(x.source_start, y.source_start),
+							source_size: Vec2::new(x.source_size, y.source_size),
+						});
+					}
+				}
+			}
+		}
+
+		patches
+	}
+
+	/// Split one part of one axis into segments.
+	fn axis_segments(&self, axis: usize, part: SlicePart, target: f32, fill: SliceFill) -> Vec<Segment> {
+		let border_min = self.border_min[axis] as f32;
+		let border_max = self.border_max[axis] as f32;
+		let source = self.sprite.size[axis] as f32;
+
+		// Shrink the borders if they don't fit, leaving no room for the middle.
+		let border_scale = (target / (border_min + border_max)).min(1.0);
+		let min_size = border_min * border_scale;
+		let max_size = border_max * border_scale;
+		let middle_size = target - min_size - max_size;
+		let source_middle = source - border_min - border_max;
+
+		let segments = match part {
+			SlicePart::Min => vec![Segment { start: 0.0, size: min_size, source_start: 0.0, source_size: border_min }],
+			SlicePart::Max => vec![Segment { start: target - max_size, size: max_size, source_start: source - border_max, source_size: border_max }],
+
+			// The borders cover the whole sprite, so there's nothing to tile - stretch the seam between them instead.
+			SlicePart::Middle if source_middle <= 0.0 => vec![Segment { start: min_size, size: middle_size, source_start: border_min, source_size: 0.0 }],
+
+			SlicePart::Middle => match fill {
+				SliceFill::Stretch => vec![Segment { start: min_size, size: middle_size, source_start: border_min, source_size: source_middle }],
+
+				SliceFill::Tile => {
+					let repetitions = (middle_size / source_middle).ceil().max(0.0) as usize;
+
+					(0..repetitions)
+						.map(|index| {
+							let offset = index as f32 * source_middle;
+							let size = source_middle.min(middle_size - offset);
+							Segment { start: min_size + offset, size, source_start: border_min, source_size: size }
+						})
+						.collect()
+				}
+			},
+		};
+
+		segments.into_iter()
+			.filter(|segment| segment.size > 0.0)
+			.collect()
+	}
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum SlicePart {
+	Min,
+	Middle,
+	Max,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Segment {
+	start: f32,
+	size: f32,
+	source_start: f32,
+	source_size: f32,
+}
+
+
+/// Generate a `SpriteInstance` for each patch of a nine slice drawn at `size` pixels.
+///
+/// As with `SpriteInstance::new`, the whole rectangle is normalised to a unit square around the sprite's anchor before
+/// `transform` is applied, so a transform with axes of `size` length keeps the corners at their native size.
+pub(crate) fn nine_slice_instances(slice: &NineSlice, size: Vec2, transform: &Mat3, color: Vec4, texture_size: IVec2) -> Vec<SpriteInstance> {
+	let sprite = &slice.sprite;
+	let anchor_offset = sprite.anchor_fraction();
+	let texture_size = texture_size.as_vec2();
+
+	slice.patches(size).into_iter()
+		.map(|patch| {
+			let local_start = patch.start / size - anchor_offset;
+			let local_size = patch.size / size;
+
+			// Map the patch's unit square into the panel's, then place that with `transform`.
+			let patch_transform = *transform * Mat3::from_scale_angle_translation(local_size, 0.0, local_start);
+
+			let uv_offset = (sprite.start.as_vec2() + patch.source_start) / texture_size;
+			let uv_scale = patch.source_size / texture_size;
+
+			SpriteInstance::from_quad_transform(&patch_transform, uv_offset, uv_scale, sprite.layer, color)
+		})
+		.collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Anchor;
+
+	/// A 16x16 sprite with 4 pixel borders, leaving an 8x8 middle.
+	fn panel() -> NineSlice {
+		NineSlice::uniform(Sprite::new(IVec2::new(16, 32), IVec2::splat(16)), 4)
+	}
+
+	/// Check patches lie within `size` without overlapping, and together cover all of it.
+	fn assert_covers(patches: &[SlicePatch], size: Vec2) {
+		for (index, patch) in patches.iter().enumerate() {
+			assert!(patch.size.cmpgt(Vec2::ZERO).all(), "empty patch {patch:?}");
+			assert!(patch.start.cmpge(Vec2::ZERO).all() && (patch.start + patch.size).cmple(size).all(), "{patch:?} is outside {size}");
+
+			for other in &patches[..index] {
+				let overlap = (patch.start + patch.size).min(other.start + other.size) - patch.start.max(other.start);
+				assert!(overlap.cmple(Vec2::ZERO).any(), "{patch:?} overlaps {other:?}");
+			}
+		}
+
+		let area: f32 = patches.iter().map(|patch| patch.size.x * patch.size.y).sum();
+		assert_eq!(area, size.x * size.y);
+	}
+
+	#[test]
+	fn stretch_makes_nine_patches() {
+		let size = Vec2::new(40.0, 30.0);
+		let patches = panel().patches(size);
+
+		assert_eq!(patches.len(), 9);
+		assert_covers(&patches, size);
+
+		// Bottom-left corner at native size, then the bottom edge stretched across, and the center in the middle.
+		assert_eq!(patches[0], SlicePatch {
+			start: Vec2::ZERO,
+			size: Vec2::splat(4.0),
+			source_start: Vec2::ZERO,
+			source_size: Vec2::splat(4.0),
+		});
+
+		assert_eq!(patches[1].size, Vec2::new(32.0, 4.0));
+		assert_eq!(patches[1].source_size, Vec2::new(8.0, 4.0));
+
+		assert_eq!(patches[4], SlicePatch {
+			start: Vec2::splat(4.0),
+			size: Vec2::new(32.0, 22.0),
+			source_start: Vec2::splat(4.0),
+			source_size: Vec2::splat(8.0),
+		});
+
+		assert_eq!(patches[8].start, Vec2::new(36.0, 26.0));
+		assert_eq!(patches[8].source_start, Vec2::splat(12.0));
+	}
+
+	#[test]
+	fn tile_crops_last_repetition() {
+		let slice = panel().with_edge_fill(SliceFill::Tile);
+		let size = Vec2::new(30.0, 16.0);
+		let patches = slice.patches(size);
+
+		assert_covers(&patches, size);
+
+		// 4 corners, 3 tiles along each of the top and bottom, 1 on each side, and the stretched center.
+		assert_eq!(patches.len(), 13);
+
+		let bottom_edge: Vec<&SlicePatch> = patches.iter()
+			.filter(|patch| patch.start.y == 0.0 && patch.start.x > 0.0 && patch.start.x < 26.0)
+			.collect();
+
+		let starts: Vec<f32> = bottom_edge.iter().map(|patch| patch.start.x).collect();
+		let widths: Vec<f32> = bottom_edge.iter().map(|patch| patch.size.x).collect();
+		assert_eq!(starts, [4.0, 12.0, 20.0]);
+		assert_eq!(widths, [8.0, 8.0, 6.0]);
+
+		// Every tile starts at the same place in the source, and the last only shows as much as fits.
+		assert!(bottom_edge.iter().all(|patch| patch.source_start.x == 4.0));
+		assert_eq!(bottom_edge[2].source_size, Vec2::new(6.0, 4.0));
+
+		// The cropped uvs make it through to the instances.
+		let texture_size = IVec2::splat(64);
+		let instances = nine_slice_instances(&slice, size, &Mat3::from_scale(size), Vec4::ONE, texture_size);
+		let last_tile = patches.iter().position(|patch| patch == bottom_edge[2]).unwrap();
+
+		assert_eq!(instances.len(), patches.len());
+		assert_eq!(instances[last_tile].uv_offset, Vec2::new(20.0, 32.0) / 64.0);
+		assert_eq!(instances[last_tile].uv_scale, Vec2::new(6.0, 4.0) / 64.0);
+	}
+
+	#[test]
+	fn three_slice_makes_three_patches() {
+		let slice = NineSlice::horizontal(Sprite::new(IVec2::ZERO, IVec2::new(16, 8)), 4, 4);
+		let size = Vec2::new(40.0, 8.0);
+		let patches = slice.patches(size);
+
+		assert_eq!(patches.len(), 3);
+		assert_covers(&patches, size);
+
+		let widths: Vec<f32> = patches.iter().map(|patch| patch.size.x).collect();
+		assert_eq!(widths, [4.0, 32.0, 4.0]);
+		assert!(patches.iter().all(|patch| patch.source_size.y == 8.0));
+	}
+
+	#[test]
+	fn small_targets_shrink_borders() {
+		// Narrower and shorter than the borders, so they're scaled to fit and the middle is dropped.
+		let size = Vec2::new(6.0, 2.0);
+		let patches = panel().patches(size);
+
+		assert_eq!(patches.len(), 4);
+		assert_covers(&patches, size);
+		assert!(patches.iter().all(|patch| patch.size == Vec2::new(3.0, 1.0)));
+
+		// Only tall enough for the borders - no zero height middle row.
+		let size = Vec2::new(20.0, 8.0);
+		let patches = panel().with_center_fill(SliceFill::Tile).patches(size);
+		assert_eq!(patches.len(), 6);
+		assert_covers(&patches, size);
+
+		assert!(panel().patches(Vec2::ZERO).is_empty());
+		assert!(panel().patches(Vec2::new(-10.0, 10.0)).is_empty());
+	}
+
+	#[test]
+	fn instances_are_placed_around_the_anchor() {
+		let slice = NineSlice { sprite: panel().sprite.with_anchor(Anchor::BottomLeft), ..panel() };
+		let size = Vec2::new(40.0, 30.0);
+		let instances = nine_slice_instances(&slice, size, &Mat3::from_scale(size), Vec4::ONE, IVec2::splat(64));
+
+		// With axes `size` long, patches land at their pixel positions.
+		assert_eq!(instances[0].origin.truncate(), Vec2::ZERO);
+		assert_eq!(instances[8].origin.truncate(), Vec2::new(36.0, 26.0));
+		assert_eq!(instances[8].x_axis.truncate() + instances[8].y_axis.truncate(), Vec2::splat(4.0));
+	}
+}
@@ -0,0 +1,283 @@
+//! Rectangle packing, for building atlases.
+//!
+//! Packers only deal in integer rectangles - they don't know about images or textures, so they can be used both
+//! offline and at runtime. Positions are measured from whichever corner the caller likes, so long as it's consistent.
+
+use glam::IVec2;
+
+
+pub trait RectPacker {
+	/// Find space for a rectangle of `size`, returning its position or `None` if it doesn't fit.
+	fn insert(&mut self, size: IVec2) -> Option<IVec2>;
+
+	/// Forget every inserted rectangle.
+	fn clear(&mut self);
+
+	/// The area rectangles are packed into.
+	fn size(&self) -> IVec2;
+
+	/// Extend the packable area. Existing rectangles keep their positions.
+	/// Shrinking isn't supported - `size` must be at least as large as the current size on both axes.
+	fn grow(&mut self, size: IVec2);
+}
+
+
+
+/// Packs rectangles along a 'skyline' - the top edge of everything packed so far - placing each as low as possible.
+///
+/// Fast and compact for rectangles of similar height, such as glyphs, but can't fill gaps left beneath the skyline.
+#[derive(Clone, Debug)]
+pub struct SkylinePacker {
+	size: IVec2,
+
+	/// Sorted by x, and always covering the full width.
+	segments: Vec<SkylineSegment>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SkylineSegment {
+	x: i32,
+	y: i32,
+	width: i32,
+}
+
+impl SkylinePacker {
+	pub fn new(size: IVec2) -> SkylinePacker {
+		SkylinePacker {
+			size,
+			segments: vec![SkylineSegment { x: 0, y: 0, width: size.x }],
+		}
+	}
+
+	/// The height a rectangle of `width` would sit at if placed at the start of segment `index`.
+	fn fit(&self, index: usize, width: i32) -> Option<i32> {
+		let x = self.segments[index].x;
+		if x + width > self.size.x {
+			return None
+		}
+
+		let mut y = 0;
+		let mut remaining = width;
+
+		for segment in &self.segments[index..] {
+			if remaining <= 0 {
+				break
+			}
+
+			y = y.max(segment.y);
+			remaining -= segment.width;
+		}
+
+		Some(y)
+	}
+
+	/// Merge neighbouring segments at the same height.
+	fn merge_segments(&mut self) {
+		self.segments.dedup_by(|right, left| {
+			let same_height = left.y == right.y;
+			if same_height {
+				left.width += right.width;
+			}
+
+			same_height
+		});
+	}
+}
+
+impl RectPacker for SkylinePacker {
+	fn insert(&mut self, size: IVec2) -> Option<IVec2> {
+		if size.cmple(IVec2::ZERO).any() {
+			return None
+		}
+
+		// Bottom-left heuristic: lowest top edge, then narrowest segment to keep the skyline tidy.
+		let (index, y) = (0..self.segments.len())
+			.filter_map(|index| Some((index, self.fit(index, size.x)?)))
+			.filter(|&(_, y)| y + size.y <= self.size.y)
+			.min_by_key(|&(index, y)| (y + size.y, self.segments[index].width))?;
+
+		let position = IVec2::new(self.segments[index].x, y);
+		let new_segment = SkylineSegment { x: position.x, y: y + size.y, width: size.x };
+
+		// Shorten or remove the segments the new rectangle now covers.
+		let right = position.x + size.x;
+		let mut next = index;
+		while next < self.segments.len() && self.segments[next].x < right {
+			let segment = &mut self.segments[next];
+			let segment_right = segment.x + segment.width;
+
+			if segment_right <= right {
+				self.segments.remove(next);
+			} else {
+				segment.width = segment_right - right;
+				segment.x = right;
+				next += 1;
+			}
+		}
+
+		self.segments.insert(index, new_segment);
+
+		self.merge_segments();
+
+		Some(position)
+	}
+
+	fn clear(&mut self) {
+		*self = SkylinePacker::new(self.size);
+	}
+
+	fn size(&self) -> IVec2 {
+		self.size
+	}
+
+	fn grow(&mut self, size: IVec2) {
+		assert!(size.cmpge(self.size).all(), "packers can't shrink");
+
+		let extra_width = size.x - self.size.x;
+		if extra_width > 0 {
+			self.segments.push(SkylineSegment { x: self.size.x, y: 0, width: extra_width });
+			self.merge_segments();
+		}
+
+		self.size = size;
+	}
+}
+
+
+
+/// Tracks every maximal free rectangle, placing each new rectangle where it leaves the shortest leftover side.
+///
+/// Slower than `SkylinePacker`, but packs mixed sizes much more tightly.
+#[derive(Clone, Debug)]
+pub struct MaxRectsPacker {
+	size: IVec2,
+	free_rects: Vec<Rect>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Rect {
+	start: IVec2,
+	size: IVec2,
+}
+
+impl Rect {
+	fn end(&self) -> IVec2 {
+		self.start + self.size
+	}
+
+	fn intersects(&self, other: &Rect) -> bool {
+		self.start.cmplt(other.end()).all() && other.start.cmplt(self.end()).all()
+	}
+
+	fn contains(&self, other: &Rect) -> bool {
+		self.start.cmple(other.start).all() && other.end().cmple(self.end()).all()
+	}
+}
+
+impl MaxRectsPacker {
+	pub fn new(size: IVec2) -> MaxRectsPacker {
+		MaxRectsPacker {
+			size,
+			free_rects: vec![Rect { start: IVec2::ZERO, size }],
+		}
+	}
+
+	/// Remove `used` from every free rectangle it overlaps, replacing each with the up to four maximal rectangles
+	/// left around it.
+	fn split_free_rects(&mut self, used: Rect) {
+		let mut new_rects = Vec::new();
+
+		self.free_rects.retain(|free| {
+			if !free.intersects(&used) {
+				return true
+			}
+
+			let (free_end, used_end) = (free.end(), used.end());
+
+			if used.start.x > free.start.x {
+				new_rects.push(Rect { start: free.start, size: IVec2::new(used.start.x - free.start.x, free.size.y) });
+			}
+
+			if used_end.x < free_end.x {
+				new_rects.push(Rect { start: IVec2::new(used_end.x, free.start.y), size: IVec2::new(free_end.x - used_end.x, free.size.y) });
+			}
+
+			if used.start.y > free.start.y {
+				new_rects.push(Rect { start: free.start, size: IVec2::new(free.size.x, used.start.y - free.start.y) });
+			}
+
+			if used_end.y < free_end.y {
+				new_rects.push(Rect { start: IVec2::new(free.start.x, used_end.y), size: IVec2::new(free.size.x, free_end.y - used_end.y) });
+			}
+
+			false
+		});
+
+		self.free_rects.extend(new_rects);
+		self.prune_free_rects();
+	}
+
+	/// Remove free rectangles entirely contained within another.
+	fn prune_free_rects(&mut self) {
+		let mut index = 0;
+		while index < self.free_rects.len() {
+			let rect = self.free_rects[index];
+			let redundant = self.free_rects.iter()
+				.enumerate()
+				.any(|(other_index, other)| other_index != index && other.contains(&rect) && (other != &rect || other_index < index));
+
+			if redundant {
+				self.free_rects.swap_remove(index);
+			} else {
+				index += 1;
+			}
+		}
+	}
+}
+
+impl RectPacker for MaxRectsPacker {
+	fn insert(&mut self, size: IVec2) -> Option<IVec2> {
+		if size.cmple(IVec2::ZERO).any() {
+			return None
+		}
+
+		// Best short side fit, breaking ties with the long side.
+		let best = self.free_rects.iter()
+			.filter(|free| size.cmple(free.size).all())
+			.min_by_key(|free| {
+				let leftover = free.size - size;
+				(leftover.min_element(), leftover.max_element())
+			})?;
+
+		let used = Rect { start: best.start, size };
+		self.split_free_rects(used);
+
+		Some(used.start)
+	}
+
+	fn clear(&mut self) {
+		*self = MaxRectsPacker::new(self.size);
+	}
+
+	fn size(&self) -> IVec2 {
+		self.size
+	}
+
+	fn grow(&mut self, size: IVec2) {
+		assert!(size.cmpge(self.size).all(), "packers can't shrink");
+
+		// The new space is two overlapping strips along the right and far edges.
+		// Free rectangles touching the old edges could be extended into them, but keeping them separate is simpler
+		// and only costs a little packing efficiency.
+		if size.x > self.size.x {
+			self.free_rects.push(Rect { start: IVec2::new(self.size.x, 0), size: IVec2::new(size.x - self.size.x, size.y) });
+		}
+
+		if size.y > self.size.y {
+			self.free_rects.push(Rect { start: IVec2::new(0, self.size.y), size: IVec2::new(size.x, size.y - self.size.y) });
+		}
+
+		self.size = size;
+		self.prune_free_rects();
+	}
+}
@@ -1,39 +1,129 @@
 #version 450
 
 // `binding` here refers to the texture unit, and corresponds to the value passed to `glBindTextureUnit`
+// ARRAY_TEXTURE is defined when compiling the variant used with `ArrayTexture`s.
+#ifdef ARRAY_TEXTURE
+layout(binding=0) uniform sampler2DArray u_texture;
+#else
 layout(binding=0) uniform sampler2D u_texture;
+#endif
 
 
-in vec2 v_uv;
+// One of the BLEND_ defines picks how the output is prepared for blending - see `BlendMode`.
+// Without any of them, alpha is dithered.
+#if !defined(BLEND_OPAQUE) && !defined(BLEND_ALPHA) && !defined(BLEND_PREMULTIPLIED_ALPHA) && !defined(BLEND_ADDITIVE) && !defined(BLEND_MULTIPLY)
+#define BLEND_DITHERED
+#endif
+
+// POINT_SPRITE is defined when compiling the variant used with `PointBackend`, which works out uvs per fragment.
+#ifdef POINT_SPRITE
+flat in vec4 v_uv_rect;
+flat in float v_layer;
+flat in vec2 v_point_scale;
+#else
+// The layer of an array texture is stored in z.
+in vec3 v_uv;
+in vec3 v_world_position;
+#endif
+
 in vec4 v_color;
 
 layout(location=0) out vec4 o_color;
 
 
 
+#ifdef BLEND_DITHERED
+// Set for each batch by `SpriteRenderer` and `StaticSpriteBatch` - see `Dither`. Locations start at 1, since
+// `PointBackend` uses 0. Uniforms start out zero and then keep whatever was last set, so `u_dither_size` is only zero
+// if this program is used by something else before either of them has drawn with it.
+layout(binding=1) uniform sampler2D u_dither_thresholds;
+layout(location=1) uniform int u_dither_size;
+// In the order of `DitherSpace` - 0 for screen, 1 for world, 2 for object.
+layout(location=2) uniform int u_dither_space;
+layout(location=3) uniform float u_dither_density;
+layout(location=4) uniform ivec2 u_dither_offset;
+
+// `DitherPattern::Bayer2x2`'s thresholds, for when nothing has been set yet.
+const float[] c_bayer_2x2 = float[](0.125, 0.625, 0.875, 0.375);
 
-const float[] c_bayer_2x2 = float[](0.25, 0.75, 1.0, 0.5);
+float dither_threshold(vec3 uv) {
+	if (u_dither_size == 0) {
+		ivec2 cell = ivec2(gl_FragCoord.xy) & 1;
+		return c_bayer_2x2[cell.x + cell.y * 2];
+	}
 
-void dither2x2_discard(float value) {
-	ivec2 position = ivec2(gl_FragCoord.xy);
-	int x = position.x % 2;
-	int y = position.y % 2;
-	int index = x + y * 2;
+	vec2 position = gl_FragCoord.xy;
 
-	float limit = c_bayer_2x2[index];
+#ifdef POINT_SPRITE
+	// Point sprite fragments have no world position, so world space falls back to object space.
+	bool object_space = u_dither_space != 0;
+#else
+	bool object_space = u_dither_space == 2;
 
-	// + 0.125 (half the distance between bayer thresholds) makes this behave more like a round.
-	// the small offset on top of that works around a weird noise seemingly introduced by vertex interpolation?
-	// Its very strange and I would like to know why this noise exists, but for now at least this makes it
-	// harder to accidentally encounter.
-	if (value + 0.1250001 < limit) {
-		discard;
+	if (u_dither_space == 1) {
+		// Project onto whichever axis-aligned plane the sprite faces most, keeping y up where possible.
+		vec3 normal = abs(cross(dFdx(v_world_position), dFdy(v_world_position)));
+
+		if (normal.x > max(normal.y, normal.z)) {
+			position = v_world_position.zy;
+		} else if (normal.y > normal.z) {
+			position = v_world_position.xz;
+		} else {
+			position = v_world_position.xy;
+		}
 	}
+#endif
+
+	if (object_space) {
+		position = uv.xy * vec2(textureSize(u_texture, 0).xy);
+	}
+
+	// Sizes are powers of two, so masking wraps cells into the tile - negative ones included, unlike %.
+	ivec2 cell = (ivec2(floor(position * u_dither_density)) + u_dither_offset) & (u_dither_size - 1);
+	return texelFetch(u_dither_thresholds, cell, 0).r;
 }
+#endif
 
 
 
 void main() {
-	o_color = texture(u_texture, v_uv) * v_color;
-	dither2x2_discard(o_color.a);
+#ifdef POINT_SPRITE
+	// Points are square, so crop them to the sprite's aspect ratio.
+	vec2 local = (gl_PointCoord - 0.5) * v_point_scale + 0.5;
+	if (any(lessThan(local, vec2(0.0))) || any(greaterThan(local, vec2(1.0)))) {
+		discard;
+	}
+
+	vec3 v_uv = vec3(v_uv_rect.xy + local * v_uv_rect.zw, v_layer);
+#endif
+
+#ifdef ARRAY_TEXTURE
+	vec4 texel = texture(u_texture, v_uv);
+#else
+	vec4 texel = texture(u_texture, v_uv.xy);
+#endif
+
+#if defined(BLEND_PREMULTIPLIED_ALPHA)
+	// The texture is already premultiplied, so only the tint needs it.
+	o_color = texel * vec4(v_color.rgb * v_color.a, v_color.a);
+#else
+	o_color = texel * v_color;
+#endif
+
+#if defined(BLEND_OPAQUE)
+	if (o_color.a < 0.5) {
+		discard;
+	}
+
+	o_color.a = 1.0;
+
+#elif defined(BLEND_DITHERED)
+	if (o_color.a < dither_threshold(v_uv)) {
+		discard;
+	}
+
+#elif defined(BLEND_MULTIPLY)
+	// Blending multiplies the destination by our color, so fade towards white - no change - as alpha drops.
+	o_color.rgb = mix(vec3(1.0), o_color.rgb, o_color.a);
+#endif
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+#version 450
+
+
+// Uniform buffers must have std140 layout.
+// `binding` here is the same value passed to glBindBufferBase.
+layout(std140, binding=0) uniform Uniforms {
+	mat4 u_projection;
+};
+
+
+// Per-vertex
+layout(location=0) in vec2 a_corner;
+
+// Per-instance - must match `SpriteInstance` in sprite/src/backend/mod.rs.
+layout(location=1) in vec3 a_x_axis;
+layout(location=2) in float a_layer;
+layout(location=3) in vec3 a_y_axis;
+layout(location=4) in vec3 a_origin;
+layout(location=5) in vec2 a_uv_offset;
+layout(location=6) in vec2 a_uv_scale;
+layout(location=7) in vec4 a_color;
+
+out vec3 v_uv;
+out vec4 v_color;
+
+// For dithering in world space - see `DitherSpace::World`.
+out vec3 v_world_position;
+
+
+void main() {
+	vec3 world_pos = a_x_axis * a_corner.x + a_y_axis * a_corner.y + a_origin;
+
+	gl_Position = u_projection * vec4(world_pos, 1.0);
+	v_uv = vec3(a_uv_scale * a_corner + a_uv_offset, a_layer);
+	v_color = a_color;
+	v_world_position = world_pos;
+}
@@ -0,0 +1,48 @@
+#version 450
+
+
+// Uniform buffers must have std140 layout.
+// `binding` here is the same value passed to glBindBufferBase.
+layout(std140, binding=0) uniform Uniforms {
+	mat4 u_projection;
+};
+
+// Set by `PointBackend::draw` from the current viewport.
+layout(location=0) uniform float u_viewport_height;
+
+
+// One vertex per sprite - must match `SpriteInstance` in sprite/src/backend/mod.rs.
+layout(location=0) in vec3 a_x_axis;
+layout(location=1) in float a_layer;
+layout(location=2) in vec3 a_y_axis;
+layout(location=3) in vec3 a_origin;
+layout(location=4) in vec2 a_uv_offset;
+layout(location=5) in vec2 a_uv_scale;
+layout(location=6) in vec4 a_color;
+
+// xy is the uv offset, zw the uv scale.
+flat out vec4 v_uv_rect;
+flat out float v_layer;
+
+// How much larger the point is than the sprite along each axis, so the fragment shader can crop it.
+flat out vec2 v_point_scale;
+
+out vec4 v_color;
+
+
+void main() {
+	vec3 center = a_origin + (a_x_axis + a_y_axis) * 0.5;
+	vec2 extent = vec2(length(a_x_axis), length(a_y_axis));
+	float world_size = max(extent.x, extent.y);
+
+	gl_Position = u_projection * vec4(center, 1.0);
+
+	// How much clip space y changes per world unit, regardless of camera orientation - see `screen_pixels_per_unit` in billboard.rs.
+	float clip_scale = length(vec3(u_projection[0][1], u_projection[1][1], u_projection[2][1]));
+	gl_PointSize = world_size * clip_scale * u_viewport_height * 0.5 / gl_Position.w;
+
+	v_uv_rect = vec4(a_uv_offset, a_uv_scale);
+	v_layer = a_layer;
+	v_point_scale = world_size / max(extent, vec2(1e-6));
+	v_color = a_color;
+}
@@ -8,22 +8,34 @@ layout(std140, binding=0) uniform Uniforms {
 };
 
 
+// Must match `SpriteInstance` in sprite/src/backend/mod.rs.
+// A mat3 would have each column padded to 16 bytes, so the columns are stored separately instead, with the spare
+// fourth component of the first used for the array texture layer.
 struct Sprite {
-	mat3 transform;
-	vec4 color;
-	vec2 uv_scale;
+	vec3 x_axis;
+	float layer;
+	vec3 y_axis;
+	float _padding0;
+	vec3 origin;
+	float _padding1;
+
 	vec2 uv_offset;
+	vec2 uv_scale;
+	vec4 color;
 };
 
-layout(std430, binding=0) buffer Sprites {
+layout(std430, binding=0) readonly buffer Sprites {
 	Sprite u_sprites[];
 };
 
 
-out vec2 v_uv;
+out vec3 v_uv;
 out vec4 v_color;
 
-const vec2[4] g_uvs = {
+// For dithering in world space - see `DitherSpace::World`.
+out vec3 v_world_position;
+
+const vec2[4] g_corners = {
 	{0.0, 0.0},
 	{1.0, 0.0},
 	{1.0, 1.0},
@@ -33,17 +45,16 @@ const vec2[4] g_uvs = {
 const uint g_indices[6] = {0, 1, 2, 0, 2, 3};
 
 void main() {
-	const uint sprite_index = gl_VertexID / 6;
+	const uint sprite_index = uint(gl_VertexID) / 6;
 	const Sprite sprite = u_sprites[sprite_index];
 
-	const uint index = g_indices[gl_VertexID % 6];
-	const vec2 uv = g_uvs[index];
-	const vec2 local_pos = uv - vec2(0.5);
+	const uint index = g_indices[uint(gl_VertexID) % 6];
+	const vec2 corner = g_corners[index];
 
-	const vec3 world_pos = sprite.transform * vec3(local_pos, 1.0);
+	const vec3 world_pos = sprite.x_axis * corner.x + sprite.y_axis * corner.y + sprite.origin;
 
 	gl_Position = u_projection * vec4(world_pos, 1.0);
-	v_uv = sprite.uv_scale * uv + sprite.uv_offset;
+	v_uv = vec3(sprite.uv_scale * corner + sprite.uv_offset, sprite.layer);
 	v_color = sprite.color;
+	v_world_position = world_pos;
 }
-
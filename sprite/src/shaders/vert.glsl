@@ -10,14 +10,20 @@ layout(std140, binding=0) uniform Uniforms {
 
 layout(location=0) in vec3 a_position;
 layout(location=1) in vec2 a_uv;
+layout(location=2) in float a_layer;
+layout(location=3) in vec4 a_color;
 
-out vec2 v_uv;
+out vec3 v_uv;
 out vec4 v_color;
 
+// For dithering in world space - see `DitherSpace::World`.
+out vec3 v_world_position;
+
 
 void main() {
 	gl_Position = u_projection * vec4(a_position, 1.0);
-	v_uv = a_uv;
-	v_color = vec4(1.0);
+	v_uv = vec3(a_uv, a_layer);
+	v_color = a_color;
+	v_world_position = a_position;
 }
 
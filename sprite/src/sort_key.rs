@@ -0,0 +1,165 @@
+use std::cmp::Ordering;
+use crate::{BlendMode, Dither};
+
+
+/// Where a queued sprite goes in `SpriteRenderer`'s draw order.
+///
+/// Layers are drawn in ascending order. Within a layer, opaque sprites are drawn first, front-to-back so the depth test
+/// can skip hidden fragments. Transparent sprites follow, back-to-front so they blend over whatever is behind them -
+/// see `BlendMode::is_transparent`. Sprites that compare equal keep their submission order, as long as the sort is
+/// stable.
+///
+/// Opaque sprites at the same depth - e.g., everything in an orthographic scene - are grouped by blend mode, texture
+/// and, for `BlendMode::Dithered`, `Dither`, so they can share draw calls.
+///
+/// ```
+/// # use sprite::{BlendMode, SortKey};
+/// let keys = [
+///     SortKey { layer: 0, blend_mode: BlendMode::Alpha, depth: 2.0, texture_handle: 1, ..SortKey::default() },
+///     SortKey { layer: 1, blend_mode: BlendMode::Opaque, depth: 5.0, texture_handle: 1, ..SortKey::default() },
+///     SortKey { layer: 0, blend_mode: BlendMode::Opaque, depth: 3.0, texture_handle: 1, ..SortKey::default() },
+///     SortKey { layer: 0, blend_mode: BlendMode::Alpha, depth: 4.0, texture_handle: 1, ..SortKey::default() },
+///     SortKey { layer: 0, blend_mode: BlendMode::Opaque, depth: 1.0, texture_handle: 2, ..SortKey::default() },
+/// ];
+///
+/// let mut order: Vec<usize> = (0..keys.len()).collect();
+/// order.sort_by(|&a, &b| keys[a].cmp_draw_order(&keys[b]));
+///
+/// // Opaque front-to-back, then transparent back-to-front, then the next layer.
+/// assert_eq!(order, [4, 2, 3, 0, 1]);
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SortKey {
+	pub layer: i32,
+	pub blend_mode: BlendMode,
+
+	/// Distance in front of the camera, along the direction it's facing.
+	pub depth: f32,
+
+	pub texture_handle: u32,
+
+	/// Only compared between `BlendMode::Dithered` sprites - `SpriteRenderer` leaves it at `Dither::default()` for
+	/// every other mode.
+	pub dither: Dither,
+}
+
+impl SortKey {
+	pub fn cmp_draw_order(&self, other: &SortKey) -> Ordering {
+		let transparent = self.blend_mode.is_transparent();
+
+		self.layer.cmp(&other.layer)
+			.then(transparent.cmp(&other.blend_mode.is_transparent()))
+			.then_with(|| match transparent {
+				true => other.depth.total_cmp(&self.depth),
+				false => self.depth.total_cmp(&other.depth)
+					.then(self.blend_mode.cmp(&other.blend_mode))
+					.then(self.texture_handle.cmp(&other.texture_handle))
+					.then_with(|| match self.blend_mode {
+						BlendMode::Dithered => cmp_dither(&self.dither, &other.dither),
+						_ => Ordering::Equal,
+					}),
+			})
+	}
+}
+
+/// Any consistent order will do, so long as equal dithers end up next to each other.
+fn cmp_dither(a: &Dither, b: &Dither) -> Ordering {
+	a.pattern.cmp(&b.pattern)
+		.then(a.space.cmp(&b.space))
+		.then(a.density.total_cmp(&b.density))
+		.then(a.temporal.cmp(&b.temporal))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DitherPattern;
+
+	fn key(layer: i32, blend_mode: BlendMode, depth: f32, texture_handle: u32) -> SortKey {
+		SortKey { layer, blend_mode, depth, texture_handle, dither: Dither::default() }
+	}
+
+	/// Indices of `keys` in draw order, sorted stably.
+	fn draw_order(keys: &[SortKey]) -> Vec<usize> {
+		let mut order: Vec<usize> = (0..keys.len()).collect();
+		order.sort_by(|&a, &b| keys[a].cmp_draw_order(&keys[b]));
+		order
+	}
+
+	#[test]
+	fn layers_come_first() {
+		let keys = [
+			key(2, BlendMode::Opaque, 1.0, 1),
+			key(0, BlendMode::Alpha, 9.0, 1),
+			key(1, BlendMode::Opaque, 1.0, 1),
+			key(-1, BlendMode::Additive, 1.0, 1),
+			key(0, BlendMode::Opaque, 5.0, 1),
+		];
+
+		assert_eq!(draw_order(&keys), [3, 4, 1, 2, 0]);
+	}
+
+	#[test]
+	fn opaque_is_front_to_back_across_textures() {
+		let keys = [
+			key(0, BlendMode::Opaque, 3.0, 1),
+			key(0, BlendMode::Opaque, 1.0, 2),
+			key(0, BlendMode::Dithered, 2.0, 1),
+			key(0, BlendMode::Opaque, 5.0, 2),
+			key(0, BlendMode::Opaque, 0.5, 1),
+		];
+
+		assert_eq!(draw_order(&keys), [4, 1, 2, 0, 3]);
+	}
+
+	#[test]
+	fn transparent_is_back_to_front_after_opaque() {
+		let keys = [
+			key(0, BlendMode::Alpha, 1.0, 1),
+			key(0, BlendMode::Additive, 4.0, 2),
+			key(0, BlendMode::Opaque, 9.0, 1),
+			key(0, BlendMode::Multiply, 2.0, 1),
+			key(0, BlendMode::PremultipliedAlpha, 3.0, 2),
+		];
+
+		assert_eq!(draw_order(&keys), [2, 1, 4, 3, 0]);
+	}
+
+	#[test]
+	fn equal_opaque_depths_are_grouped_for_batching() {
+		let dithered = |pattern| SortKey { dither: Dither::new(pattern), ..key(0, BlendMode::Dithered, 1.0, 1) };
+		let keys = [
+			key(0, BlendMode::Opaque, 1.0, 2),
+			dithered(DitherPattern::Bayer4x4),
+			key(0, BlendMode::Opaque, 1.0, 1),
+			dithered(DitherPattern::Bayer2x2),
+			key(0, BlendMode::Opaque, 1.0, 2),
+			dithered(DitherPattern::Bayer4x4),
+		];
+
+		assert_eq!(draw_order(&keys), [2, 0, 4, 3, 1, 5]);
+	}
+
+	#[test]
+	fn equal_keys_keep_submission_order() {
+		let keys = [
+			key(0, BlendMode::Alpha, 2.0, 1),
+			key(0, BlendMode::Opaque, 1.0, 1),
+			key(0, BlendMode::Alpha, 2.0, 2),
+			key(0, BlendMode::Opaque, 1.0, 1),
+			key(0, BlendMode::Alpha, 2.0, 1),
+		];
+
+		// Transparent ties aren't grouped by texture, since that could reorder sprites that overlap.
+		assert_eq!(draw_order(&keys), [1, 3, 0, 2, 4]);
+	}
+
+	#[test]
+	fn dither_is_ignored_unless_dithered() {
+		let opaque = key(0, BlendMode::Opaque, 1.0, 1);
+		let other = SortKey { dither: Dither::new(DitherPattern::BlueNoise), ..opaque };
+
+		assert_eq!(opaque.cmp_draw_order(&other), Ordering::Equal);
+	}
+}
@@ -0,0 +1,221 @@
+use glam::IVec2;
+use std::ops::RangeInclusive;
+use crate::{AtlasTexture, Sprite, SpriteAnimation, AnimationFrame};
+
+
+/// Which corner of the texture rows are counted from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GridOrigin {
+	/// Rows count down from the top, as in image editors and the source image file.
+	TopLeft,
+
+	/// Rows count up from the bottom, as in `Sprite` coordinates.
+	BottomLeft,
+}
+
+
+/// A texture divided into uniformly sized cells, with optional margin around the edge and spacing between cells.
+///
+/// Cells are numbered in reading order from `origin` - along each row first, then row by row.
+#[derive(Copy, Clone, Debug)]
+pub struct SpriteGrid {
+	pub texture_size: IVec2,
+	pub cell_size: IVec2,
+
+	/// Space between the edges of the texture and the outermost cells.
+	pub margin: IVec2,
+
+	/// Space between neighbouring cells.
+	pub spacing: IVec2,
+
+	pub origin: GridOrigin,
+}
+
+impl SpriteGrid {
+	/// A grid with no margin or spacing, counting rows from the top-left.
+	pub fn new(texture_size: IVec2, cell_size: IVec2) -> SpriteGrid {
+		assert!(cell_size.cmpgt(IVec2::ZERO).all(), "cell size must be positive");
+
+		SpriteGrid {
+			texture_size,
+			cell_size,
+			margin: IVec2::ZERO,
+			spacing: IVec2::ZERO,
+			origin: GridOrigin::TopLeft,
+		}
+	}
+
+	pub fn from_texture(texture: &impl AtlasTexture, cell_size: IVec2) -> SpriteGrid {
+		SpriteGrid::new(texture.size(), cell_size)
+	}
+
+	pub fn with_margin(mut self, margin: IVec2) -> SpriteGrid {
+		self.margin = margin;
+		self
+	}
+
+	pub fn with_spacing(mut self, spacing: IVec2) -> SpriteGrid {
+		self.spacing = spacing;
+		self
+	}
+
+	pub fn with_origin(mut self, origin: GridOrigin) -> SpriteGrid {
+		self.origin = origin;
+		self
+	}
+
+	/// The number of whole cells that fit along each axis.
+	pub fn dimensions(&self) -> IVec2 {
+		let available = self.texture_size - self.margin * 2 + self.spacing;
+		(available / (self.cell_size + self.spacing)).max(IVec2::ZERO)
+	}
+
+	pub fn len(&self) -> usize {
+		let dimensions = self.dimensions();
+		(dimensions.x * dimensions.y) as usize
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// The sprite for the cell at `cell` (column, row), or `None` if it's outside the grid.
+	pub fn sprite(&self, cell: IVec2) -> Option<Sprite> {
+		if cell.cmplt(IVec2::ZERO).any() || cell.cmpge(self.dimensions()).any() {
+			return None
+		}
+
+		let offset = self.margin + cell * (self.cell_size + self.spacing);
+
+		// Sprites are measured from the bottom-left, since textures are flipped on upload.
+		let start = match self.origin {
+			GridOrigin::BottomLeft => offset,
+			GridOrigin::TopLeft => IVec2::new(offset.x, self.texture_size.y - offset.y - self.cell_size.y),
+		};
+
+		Some(Sprite::new(start, self.cell_size))
+	}
+
+	/// The sprite for the `index`th cell in reading order.
+	pub fn sprite_at_index(&self, index: usize) -> Option<Sprite> {
+		// Checked up front, since a large enough index would wrap around to a valid row when converted to i32.
+		if index >= self.len() {
+			return None
+		}
+
+		let columns = self.dimensions().x as usize;
+
+		self.sprite(IVec2::new((index % columns) as i32, (index / columns) as i32))
+	}
+
+	/// Every cell's sprite, in reading order.
+	pub fn sprites(&self) -> impl Iterator<Item=Sprite> + '_ {
+		(0..self.len()).filter_map(|index| self.sprite_at_index(index))
+	}
+
+	/// A looping animation through the cells `frames`, in reading order, showing each for `frame_duration` seconds.
+	pub fn animation(&self, frames: RangeInclusive<usize>, frame_duration: f32) -> Option<SpriteAnimation> {
+		let frames = frames
+			.map(|index| Some(AnimationFrame {
+				sprite: self.sprite_at_index(index)?,
+				duration: frame_duration,
+				events: Vec::new(),
+			}))
+			.collect::<Option<Vec<_>>>()?;
+
+		if frames.is_empty() {
+			return None
+		}
+
+		Some(SpriteAnimation::new(frames))
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// 5x2 cells of 16x16, 2 pixels in from the edges and 4 apart. The columns fill the width exactly.
+	fn grid(origin: GridOrigin) -> SpriteGrid {
+		SpriteGrid::new(IVec2::new(100, 50), IVec2::splat(16))
+			.with_margin(IVec2::splat(2))
+			.with_spacing(IVec2::splat(4))
+			.with_origin(origin)
+	}
+
+	fn start(sprite: Option<Sprite>) -> Option<IVec2> {
+		sprite.map(|sprite| sprite.start)
+	}
+
+	#[test]
+	fn dimensions_account_for_margin_and_spacing() {
+		let grid = grid(GridOrigin::TopLeft);
+		assert_eq!(grid.dimensions(), IVec2::new(5, 2));
+		assert_eq!(grid.len(), 10);
+
+		// No spacing is needed after the last cell, but the margin is.
+		let narrower = SpriteGrid { texture_size: IVec2::new(99, 50), ..grid };
+		assert_eq!(narrower.dimensions(), IVec2::new(4, 2));
+
+		// Too small for even one cell.
+		let tiny = SpriteGrid::new(IVec2::new(8, 64), IVec2::splat(16));
+		assert_eq!(tiny.dimensions(), IVec2::new(0, 4));
+		assert!(tiny.is_empty());
+		assert!(tiny.sprite_at_index(0).is_none());
+	}
+
+	#[test]
+	fn top_left_rows_count_down() {
+		let grid = grid(GridOrigin::TopLeft);
+
+		// The first row is at the top of the texture, so its sprites start near the top in bottom-left coordinates.
+		assert_eq!(start(grid.sprite(IVec2::new(0, 0))), Some(IVec2::new(2, 32)));
+		assert_eq!(start(grid.sprite(IVec2::new(1, 1))), Some(IVec2::new(22, 12)));
+		assert_eq!(start(grid.sprite(IVec2::new(4, 1))), Some(IVec2::new(82, 12)));
+		assert_eq!(grid.sprite(IVec2::new(1, 1)).unwrap().size, IVec2::splat(16));
+	}
+
+	#[test]
+	fn bottom_left_rows_count_up() {
+		let grid = grid(GridOrigin::BottomLeft);
+
+		assert_eq!(start(grid.sprite(IVec2::new(0, 0))), Some(IVec2::new(2, 2)));
+		assert_eq!(start(grid.sprite(IVec2::new(1, 1))), Some(IVec2::new(22, 22)));
+	}
+
+	#[test]
+	fn cells_outside_the_grid_are_none() {
+		let grid = grid(GridOrigin::TopLeft);
+
+		for cell in [IVec2::new(-1, 0), IVec2::new(0, -1), IVec2::new(5, 0), IVec2::new(0, 2)] {
+			assert!(grid.sprite(cell).is_none(), "{cell}");
+		}
+	}
+
+	#[test]
+	fn indices_are_in_reading_order() {
+		let grid = grid(GridOrigin::TopLeft);
+
+		assert_eq!(start(grid.sprite_at_index(4)), start(grid.sprite(IVec2::new(4, 0))));
+		assert_eq!(start(grid.sprite_at_index(6)), start(grid.sprite(IVec2::new(1, 1))));
+		assert!(grid.sprite_at_index(10).is_none());
+		assert!(grid.sprite_at_index(usize::MAX).is_none());
+
+		let starts: Vec<IVec2> = grid.sprites().map(|sprite| sprite.start).collect();
+		assert_eq!(starts.len(), 10);
+		assert_eq!(starts[5], IVec2::new(2, 12));
+	}
+
+	#[test]
+	fn animations_need_every_cell() {
+		let grid = grid(GridOrigin::TopLeft);
+
+		let animation = grid.animation(3..=6, 0.1).unwrap();
+		assert_eq!(animation.frames.len(), 4);
+		assert_eq!(animation.frames[2].sprite.start, IVec2::new(2, 12));
+
+		assert!(grid.animation(8..=10, 0.1).is_none());
+	}
+}
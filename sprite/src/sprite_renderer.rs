@@ -0,0 +1,517 @@
+use glam::{Vec2, Vec3, Vec4, Mat3, Mat4, IVec2};
+use half::f16;
+use crate::{AtlasTexture, Billboard, BillboardSize, BlendMode, Dither, SortKey, SpriteShaders, Transform3D, Vertex, VertexAttribute};
+use crate::dither::DitherTextures;
+use crate::billboard::screen_pixels_per_unit;
+use crate::backend::{SpriteBackend, SpriteInstance, VertexBackend};
+use crate::nine_slice::{NineSlice, nine_slice_instances};
+
+
+/// A vertex type that sprite quads can be built from.
+pub trait SpriteVertex: Vertex {
+	/// `layer` selects the layer of an `ArrayTexture`, and should be ignored by shaders sampling a plain `Texture`.
+	/// `color` is multiplied with the sampled texel - white leaves it unchanged.
+	fn from_sprite(position: Vec3, uv: Vec2, layer: u32, color: Vec4) -> Self;
+}
+
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TexturedVertex {
+	pub position: Vec3,
+	pub uv: Vec2,
+	pub layer: f32,
+	// Not a `Vec4`, since its 16 byte alignment would pad the vertex out to 48 bytes.
+	pub color: [f32; 4],
+}
+
+impl Vertex for TexturedVertex {
+	const ATTRIBUTES: &'static [VertexAttribute] = &[
+		VertexAttribute::new(3, gl::FLOAT, std::mem::offset_of!(TexturedVertex, position)),
+		VertexAttribute::new(2, gl::FLOAT, std::mem::offset_of!(TexturedVertex, uv)),
+		VertexAttribute::new(1, gl::FLOAT, std::mem::offset_of!(TexturedVertex, layer)),
+		VertexAttribute::new(4, gl::FLOAT, std::mem::offset_of!(TexturedVertex, color)),
+	];
+
+	fn new(position: Vec3, uv: Vec2) -> TexturedVertex {
+		TexturedVertex::from_sprite(position, uv, 0, Vec4::ONE)
+	}
+}
+
+impl SpriteVertex for TexturedVertex {
+	fn from_sprite(position: Vec3, uv: Vec2, layer: u32, color: Vec4) -> TexturedVertex {
+		TexturedVertex {position, uv, layer: layer as f32, color: color.to_array()}
+	}
+}
+
+
+/// A 16 byte alternative to `TexturedVertex`'s 40 bytes.
+/// Positions are stored as half floats, which are exact for integers up to 2048 but lose precision quickly beyond
+/// a few hundred units from the origin. Uvs are stored as normalized u16s, which is well below a texel of error
+/// for any atlas up to 65535 pixels wide. Colors are stored as normalized u8s, and so are clamped to [0, 1].
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct CompactTexturedVertex {
+	pub position: [f16; 3],
+	// Fills what would otherwise be padding, to keep `uv` 4 byte aligned.
+	pub layer: u16,
+	pub uv: [u16; 2],
+	pub color: [u8; 4],
+}
+
+impl CompactTexturedVertex {
+	pub fn position(&self) -> Vec3 {
+		Vec3::from(self.position.map(f16::to_f32))
+	}
+
+	pub fn uv(&self) -> Vec2 {
+		Vec2::new(self.uv[0] as f32, self.uv[1] as f32) / u16::MAX as f32
+	}
+
+	pub fn color(&self) -> Vec4 {
+		Vec4::from(self.color.map(|channel| channel as f32)) / u8::MAX as f32
+	}
+}
+
+impl Vertex for CompactTexturedVertex {
+	const ATTRIBUTES: &'static [VertexAttribute] = &[
+		VertexAttribute::new(3, gl::HALF_FLOAT, std::mem::offset_of!(CompactTexturedVertex, position)),
+		VertexAttribute::normalized(2, gl::UNSIGNED_SHORT, std::mem::offset_of!(CompactTexturedVertex, uv)),
+		// Not normalized, so the shader sees the layer index as a plain float.
+		VertexAttribute::new(1, gl::UNSIGNED_SHORT, std::mem::offset_of!(CompactTexturedVertex, layer)),
+		VertexAttribute::normalized(4, gl::UNSIGNED_BYTE, std::mem::offset_of!(CompactTexturedVertex, color)),
+	];
+
+	fn new(position: Vec3, uv: Vec2) -> CompactTexturedVertex {
+		CompactTexturedVertex::from_sprite(position, uv, 0, Vec4::ONE)
+	}
+}
+
+impl SpriteVertex for CompactTexturedVertex {
+	fn from_sprite(position: Vec3, uv: Vec2, layer: u32, color: Vec4) -> CompactTexturedVertex {
+		let position = position.to_array().map(f16::from_f32);
+		let uv = (uv.clamp(Vec2::ZERO, Vec2::ONE) * u16::MAX as f32).round();
+		let color = (color.clamp(Vec4::ZERO, Vec4::ONE) * u8::MAX as f32).round();
+
+		CompactTexturedVertex {
+			position,
+			layer: layer as u16,
+			uv: [uv.x as u16, uv.y as u16],
+			color: color.to_array().map(|channel| channel as u8),
+		}
+	}
+}
+
+
+
+/// Collects sprite quads each frame, and draws them all at once in `draw`.
+///
+/// Quads are sorted before drawing as described by `SortKey` - by layer, then opaque quads front-to-back, with those at
+/// the same depth grouped by texture so they share draw calls. Transparent quads are drawn back-to-front without
+/// writing depth, so they can't hide each other.
+///
+/// `B` determines how sprites get to the GPU - see `VertexBackend`, `StorageBufferBackend`, `InstancedBackend` and `PointBackend`.
+/// Shader programs must be made from the backend's `VERTEX_SHADER`, and `frag.glsl` compiled with its `FRAGMENT_DEFINES`.
+/// After `set_shaders`, the renderer binds the right program for each batch's `BlendMode` itself. Otherwise it uses
+/// whichever program is bound when calling `draw` for every batch, which should match the blend modes used.
+pub struct SpriteRenderer<B: SpriteBackend = VertexBackend> {
+	queued_sprites: Vec<QueuedSprite>,
+
+	sprites: Vec<SpriteInstance>,
+	batches: Vec<TextureBatch>,
+	backend: B,
+
+	texture_handle: u32,
+	texture_size: IVec2,
+
+	layer: i32,
+	blend_mode: BlendMode,
+	programs: Option<[u32; BlendMode::ALL.len()]>,
+
+	dither: Dither,
+	dither_textures: DitherTextures,
+	/// Counts calls to `draw`, for `Dither::temporal`.
+	frame: u32,
+
+	pixels_per_unit: f32,
+
+	view: Mat4,
+	/// The inverse of `view`.
+	camera_transform: Mat4,
+	projection: Mat4,
+	viewport_height: f32,
+}
+
+struct QueuedSprite {
+	/// `depth` is filled in by `draw`, from the view at that point.
+	key: SortKey,
+	instance: SpriteInstance,
+}
+
+/// A run of sprites that all sample from the same texture, with the same blend mode and dither.
+struct TextureBatch {
+	texture_handle: u32,
+	blend_mode: BlendMode,
+	dither: Dither,
+	first_sprite: usize,
+}
+
+
+/// Counters describing the work done by a single `SpriteRenderer::draw`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DrawStats {
+	/// The number of draw calls made - one per run of sprites sharing a texture, blend mode and dither, in draw order.
+	pub batches: usize,
+	pub quads: usize,
+	pub uploaded_bytes: usize,
+}
+
+impl SpriteRenderer {
+	pub fn new(texture: &impl AtlasTexture) -> SpriteRenderer {
+		SpriteRenderer::with_backend(texture)
+	}
+}
+
+impl<V: SpriteVertex> SpriteRenderer<VertexBackend<V>> {
+	pub fn with_vertex_format(texture: &impl AtlasTexture) -> SpriteRenderer<VertexBackend<V>> {
+		SpriteRenderer::with_backend(texture)
+	}
+}
+
+impl<B: SpriteBackend> SpriteRenderer<B> {
+	/// The vertex shader to link with `frag.glsl` for drawing with this renderer.
+	pub const VERTEX_SHADER: &'static str = B::VERTEX_SHADER;
+
+	/// The defines to compile `frag.glsl` with for drawing with this renderer.
+	pub const FRAGMENT_DEFINES: &'static [&'static str] = B::FRAGMENT_DEFINES;
+
+	pub fn with_backend(texture: &impl AtlasTexture) -> SpriteRenderer<B> {
+		SpriteRenderer {
+			queued_sprites: Vec::new(),
+
+			sprites: Vec::new(),
+			batches: Vec::new(),
+			backend: B::new(),
+
+			texture_handle: texture.handle(),
+			texture_size: texture.size(),
+
+			layer: 0,
+			blend_mode: BlendMode::default(),
+			programs: None,
+
+			dither: Dither::default(),
+			dither_textures: Default::default(),
+			frame: 0,
+
+			pixels_per_unit: 1.0,
+
+			view: Mat4::IDENTITY,
+			camera_transform: Mat4::IDENTITY,
+			projection: Mat4::IDENTITY,
+			viewport_height: 1.0,
+		}
+	}
+
+	pub fn backend(&self) -> &B {
+		&self.backend
+	}
+
+	/// Use `texture` for all following calls to `quad_raw`. Sprites passed to `quad_raw` are then interpreted in its uv space.
+	/// The texture stays selected across frames until `set_texture` is called again.
+	/// Call it again whenever `texture` is reallocated, e.g., after a `DynamicAtlas` changes.
+	pub fn set_texture(&mut self, texture: &impl AtlasTexture) {
+		self.texture_handle = texture.handle();
+		self.texture_size = texture.size();
+	}
+
+	/// Put all following quads in `layer`. Lower layers are drawn first, but are still depth tested against later ones.
+	/// Stays set across frames. Defaults to 0.
+	pub fn set_layer(&mut self, layer: i32) {
+		self.layer = layer;
+	}
+
+	/// Draw all following quads with `blend_mode`, which also decides how they are sorted - see `SortKey`.
+	/// Stays set across frames. Defaults to `BlendMode::Dithered`.
+	pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+		self.blend_mode = blend_mode;
+	}
+
+	/// Dither all following quads drawn with `BlendMode::Dithered` as described by `dither`. Sprites with different
+	/// dithers are drawn in separate batches. Stays set across frames. Defaults to `Dither::default()`.
+	pub fn set_dither(&mut self, dither: Dither) {
+		self.dither = dither;
+	}
+
+	/// Bind programs from `shaders` to match each batch's blend mode while drawing.
+	/// Only the program handles are kept, so `shaders` must outlive any following `draw`s.
+	pub fn set_shaders(&mut self, shaders: &SpriteShaders) {
+		self.programs = Some(shaders.programs());
+	}
+
+	/// How many texture pixels make up one world unit, for sizing quads queued with `quad` and `billboard`. Defaults to 1.
+	/// Use the same value for every sprite in a scene to keep their pixels the same size.
+	pub fn set_pixels_per_unit(&mut self, pixels_per_unit: f32) {
+		assert!(pixels_per_unit > 0.0, "pixels per unit must be positive");
+		self.pixels_per_unit = pixels_per_unit;
+	}
+
+	pub fn pixels_per_unit(&self) -> f32 {
+		self.pixels_per_unit
+	}
+
+	/// Use `view` to orient all following calls to `billboard`, and to sort quads by depth in `draw`.
+	/// Stays set across frames, so call it whenever the camera moves.
+	pub fn set_view(&mut self, view: &Mat4) {
+		self.view = *view;
+		self.camera_transform = view.inverse();
+	}
+
+	/// Use `projection`, without the view matrix, and a viewport `viewport_height` pixels tall to size billboards
+	/// drawn with `BillboardSize::Screen`. Stays set across frames, so call it whenever the window is resized.
+	pub fn set_projection(&mut self, projection: &Mat4, viewport_height: f32) {
+		self.projection = *projection;
+		self.viewport_height = viewport_height;
+	}
+
+	pub fn draw(&mut self) -> DrawStats {
+		// The camera looks down -Z, so depth is the negated view space z.
+		let view_z = self.view.row(2);
+
+		for queued in &mut self.queued_sprites {
+			queued.key.depth = -view_z.dot(queued.instance.center().extend(1.0));
+		}
+
+		// The sort is stable, so sprites that compare equal keep their submission order.
+		self.queued_sprites.sort_by(|a, b| a.key.cmp_draw_order(&b.key));
+
+		for queued in self.queued_sprites.drain(..) {
+			let batch_key = (queued.key.texture_handle, queued.key.blend_mode, queued.key.dither);
+
+			if self.batches.last().map(|batch| (batch.texture_handle, batch.blend_mode, batch.dither)) != Some(batch_key) {
+				self.batches.push(TextureBatch {
+					texture_handle: queued.key.texture_handle,
+					blend_mode: queued.key.blend_mode,
+					dither: queued.key.dither,
+					first_sprite: self.sprites.len(),
+				});
+			}
+
+			self.sprites.push(queued.instance);
+		}
+
+		// Update buffers
+		let uploaded_bytes = self.backend.upload(&self.sprites);
+
+		// Draw each batch with its own texture bound.
+		let batch_ends = self.batches.iter().skip(1)
+			.map(|batch| batch.first_sprite)
+			.chain(std::iter::once(self.sprites.len()));
+
+		for (batch, end_sprite) in self.batches.iter().zip(batch_ends) {
+			unsafe {
+				// Bind our sprite atlas to 0th texture unit - matching the binding specified in frag.glsl
+				gl::BindTextureUnit(0, batch.texture_handle);
+
+				if let Some(programs) = &self.programs {
+					gl::UseProgram(programs[batch.blend_mode as usize]);
+				}
+
+				if batch.blend_mode == BlendMode::Dithered {
+					self.dither_textures.bind(&batch.dither, self.frame);
+				}
+
+				// Transparent sprites are still hidden by opaque ones in front of them, but mustn't hide what's behind them.
+				gl::DepthMask(if batch.blend_mode.is_transparent() { gl::FALSE } else { gl::TRUE });
+			}
+
+			batch.blend_mode.apply();
+			self.backend.draw(batch.first_sprite .. end_sprite);
+		}
+
+		// Leave things as we found them, for anything drawn after us.
+		BlendMode::Opaque.apply();
+
+		unsafe {
+			gl::DepthMask(gl::TRUE);
+		}
+
+		let stats = DrawStats {
+			batches: self.batches.len(),
+			quads: self.sprites.len(),
+			uploaded_bytes,
+		};
+
+		// Clear for next frame.
+		self.sprites.clear();
+		self.batches.clear();
+		self.frame = self.frame.wrapping_add(1);
+
+		stats
+	}
+
+	/// Queue `sprite` at its pixel size, placed by `transform`, using the current texture.
+	///
+	/// The quad is `sprite.size / pixels_per_unit` world units before `transform` is applied, so `transform` only needs
+	/// to orient and place it - unit length axes draw it at its natural size.
+	pub fn quad(&mut self, sprite: &Sprite, transform: &Mat3, color: Vec4) {
+		let transform = *transform * Mat3::from_scale(sprite.world_size(self.pixels_per_unit));
+		self.quad_raw(sprite, &transform, color);
+	}
+
+	/// Queue `sprite` placed by `transform`, using the current texture, `pixels_per_unit` and - for billboards - view.
+	pub fn place(&mut self, sprite: &Sprite, transform: impl Into<Transform3D>, color: Vec4) {
+		let transform = transform.into().to_mat3(sprite, self.pixels_per_unit, &self.camera_transform);
+		self.quad_raw(sprite, &transform, color);
+	}
+
+	/// Queue a quad sampling `sprite` from the texture last passed to `set_texture`, tinted by `color`.
+	/// Pass `Vec4::ONE` to draw the sprite as-is, or lower the alpha to fade it out.
+	///
+	/// The quad spans a unit square around the sprite's anchor before `transform` is applied, regardless of its pixel
+	/// size - see `quad` for a quad sized by `pixels_per_unit`.
+	pub fn quad_raw(&mut self, sprite: &Sprite, transform: &Mat3, color: Vec4) {
+		let instance = SpriteInstance::new(sprite, transform, color, self.texture_size);
+		self.queue(self.texture_handle, instance);
+	}
+
+	/// Queue a quad sampling `sprite` from `texture`, tinted by `color`.
+	pub fn draw_sprite(&mut self, texture: &impl AtlasTexture, sprite: &Sprite, transform: &Mat3, color: Vec4) {
+		let instance = SpriteInstance::new(sprite, transform, color, texture.size());
+		self.queue(texture.handle(), instance);
+	}
+
+	/// Queue a quad at `position`, turned to face the camera as described by `mode` and sized as described by `size`.
+	/// The sprite's anchor is placed at `position`, so a bottom-center anchor keeps characters standing on the ground.
+	pub fn billboard(&mut self, sprite: &Sprite, mode: Billboard, position: Vec3, size: BillboardSize, color: Vec4) {
+		let size = match size {
+			BillboardSize::World(scale) => sprite.world_size(self.pixels_per_unit) * scale,
+			BillboardSize::Fixed(size) => size,
+
+			BillboardSize::Screen(scale) => {
+				let pixels_per_unit = screen_pixels_per_unit(&(self.projection * self.view), position, self.viewport_height);
+				sprite.size.as_vec2() * scale / pixels_per_unit
+			}
+		};
+
+		let transform = mode.transform(&self.camera_transform, position, size);
+		self.quad_raw(sprite, &transform, color);
+	}
+
+	/// Queue the quads of a nine slice drawn at `size` pixels, using the current texture.
+	///
+	/// `transform` places the whole rectangle as if it were a single sprite quad, so to keep corners at their native
+	/// size its axes should be `size` long, in whatever units a pixel is.
+	pub fn nine_slice(&mut self, slice: &NineSlice, size: Vec2, transform: &Mat3, color: Vec4) {
+		for instance in nine_slice_instances(slice, size, transform, color, self.texture_size) {
+			self.queue(self.texture_handle, instance);
+		}
+	}
+
+	/// Queue `instance` in the current layer.
+	fn queue(&mut self, texture_handle: u32, instance: SpriteInstance) {
+		self.queued_sprites.push(QueuedSprite {
+			key: SortKey {
+				layer: self.layer,
+				blend_mode: self.blend_mode,
+				depth: 0.0,
+				texture_handle,
+
+				// Only dithered sprites need to be batched by it.
+				dither: if self.blend_mode == BlendMode::Dithered { self.dither } else { Dither::default() },
+			},
+
+			instance,
+		});
+	}
+}
+
+
+pub(crate) const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+/// Generate the four corners of a sprite quad, as placed by `transform`.
+pub(crate) fn quad_vertices<V: SpriteVertex>(sprite: &Sprite, transform: &Mat3, color: Vec4, texture_size: IVec2) -> [V; 4] {
+	SpriteInstance::new(sprite, transform, color, texture_size).vertices()
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Sprite {
+	pub start: IVec2,
+	pub size: IVec2,
+
+	/// The point that ends up at the origin of the transform the sprite is drawn with - and that it rotates and scales around.
+	pub anchor: Anchor,
+
+	/// Which layer of an `ArrayTexture` this sprite is in. Ignored for plain `Texture`s.
+	pub layer: u32,
+}
+
+impl Sprite {
+	pub const fn new(start: IVec2, size: IVec2) -> Sprite {
+		Sprite {
+			start,
+			size,
+			anchor: Anchor::Center,
+			layer: 0,
+		}
+	}
+
+	pub const fn with_anchor(mut self, anchor: Anchor) -> Sprite {
+		self.anchor = anchor;
+		self
+	}
+
+	/// Where `anchor` is, as a fraction of the sprite's size from its bottom-left.
+	pub fn anchor_fraction(&self) -> Vec2 {
+		self.anchor.fraction(self.size)
+	}
+
+	/// The size of this sprite in world units, at `pixels_per_unit` texture pixels per unit.
+	pub fn world_size(&self, pixels_per_unit: f32) -> Vec2 {
+		self.size.as_vec2() / pixels_per_unit
+	}
+}
+
+
+/// A point within a sprite. Named points are measured from the edges of the sprite, so stay put if it's resized or trimmed.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Anchor {
+	#[default]
+	Center,
+	BottomLeft,
+	BottomCenter,
+	BottomRight,
+	CenterLeft,
+	CenterRight,
+	TopLeft,
+	TopCenter,
+	TopRight,
+
+	/// A fraction of the sprite's size, from its bottom-left. `Vec2::splat(0.5)` is the center.
+	/// Values outside of 0 to 1 are outside the sprite.
+	Fraction(Vec2),
+
+	/// A point in pixels from the sprite's bottom-left. Can fall between pixels, e.g., 7.5 for the middle of a
+	/// 15 pixel wide sprite.
+	Pixels(Vec2),
+}
+
+impl Anchor {
+	/// Where this anchor is in a sprite of `size` pixels, as a fraction of that size from the bottom-left.
+	pub fn fraction(self, size: IVec2) -> Vec2 {
+		match self {
+			Anchor::Center => Vec2::new(0.5, 0.5),
+			Anchor::BottomLeft => Vec2::new(0.0, 0.0),
+			Anchor::BottomCenter => Vec2::new(0.5, 0.0),
+			Anchor::BottomRight => Vec2::new(1.0, 0.0),
+			Anchor::CenterLeft => Vec2::new(0.0, 0.5),
+			Anchor::CenterRight => Vec2::new(1.0, 0.5),
+			Anchor::TopLeft => Vec2::new(0.0, 1.0),
+			Anchor::TopCenter => Vec2::new(0.5, 1.0),
+			Anchor::TopRight => Vec2::new(1.0, 1.0),
+
+			Anchor::Fraction(fraction) => fraction,
+			Anchor::Pixels(pixels) => pixels / size.as_vec2(),
+		}
+	}
+}
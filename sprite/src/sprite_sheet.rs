@@ -0,0 +1,174 @@
+use glam::{IVec2, Vec2};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use crate::{Anchor, Sprite};
+
+
+/// A named collection of sprites within a single texture, along with any animation and slice data that came with them.
+///
+/// Sprites are in the same coordinate space as every other `Sprite` - bottom-left origin, as the texture is uploaded.
+/// Slice data on the other hand is kept as authored, in pixels from the top-left of the untrimmed frame.
+#[derive(Clone, Debug, Default)]
+pub struct SpriteSheet {
+	/// The size of the texture the sprites are in, in pixels.
+	pub size: IVec2,
+
+	/// The image the sprites are in, if known.
+	pub image_path: Option<PathBuf>,
+
+	/// Frames in the order they were exported. Indices into this are used by `FrameTag`s and `SliceKey`s.
+	pub frames: Vec<SpriteFrame>,
+	pub tags: Vec<FrameTag>,
+	pub slices: Vec<Slice>,
+
+	frames_by_name: HashMap<String, usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpriteFrame {
+	pub name: String,
+	pub sprite: Sprite,
+
+	/// How long this frame should be shown for when animated, in seconds.
+	pub duration: f32,
+
+	/// The size of the frame before transparent edges were trimmed.
+	pub source_size: IVec2,
+
+	/// Where the trimmed sprite sits within the untrimmed frame, in pixels from the top-left.
+	pub trim_offset: IVec2,
+
+	/// The frame's own pivot, if the atlas had one - as a fraction of the untrimmed frame, from the top-left.
+	/// Slice pivots take priority. See `SpriteSheet::apply_slice_pivots`.
+	pub pivot: Option<Vec2>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameTag {
+	pub name: String,
+	pub frames: RangeInclusive<usize>,
+	pub direction: TagDirection,
+
+	/// How many times the tagged animation should play. `None` means loop forever.
+	pub repeat: Option<u32>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TagDirection {
+	Forward,
+	Reverse,
+	PingPong,
+	PingPongReverse,
+}
+
+#[derive(Clone, Debug)]
+pub struct Slice {
+	pub name: String,
+
+	/// Sorted by `frame`. Each key applies from its frame until the next key.
+	pub keys: Vec<SliceKey>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SliceKey {
+	pub frame: usize,
+
+	pub start: IVec2,
+	pub size: IVec2,
+
+	/// The stretchable center of a nine-slice, relative to `start`.
+	pub center: Option<(IVec2, IVec2)>,
+
+	/// Relative to `start`.
+	pub pivot: Option<IVec2>,
+}
+
+
+impl SpriteSheet {
+	pub fn new(size: IVec2, frames: Vec<SpriteFrame>, tags: Vec<FrameTag>, slices: Vec<Slice>) -> SpriteSheet {
+		let frames_by_name = frames.iter()
+			.enumerate()
+			.map(|(index, frame)| (frame.name.clone(), index))
+			.collect();
+
+		SpriteSheet {
+			size,
+			image_path: None,
+			frames,
+			tags,
+			slices,
+			frames_by_name,
+		}
+	}
+
+	pub fn frame_index(&self, name: &str) -> Option<usize> {
+		self.frames_by_name.get(name).copied()
+	}
+
+	pub fn frame(&self, name: &str) -> Option<&SpriteFrame> {
+		self.frame_index(name).map(|index| &self.frames[index])
+	}
+
+	pub fn sprite(&self, name: &str) -> Option<&Sprite> {
+		self.frame(name).map(|frame| &frame.sprite)
+	}
+
+	pub fn tag(&self, name: &str) -> Option<&FrameTag> {
+		self.tags.iter().find(|tag| tag.name == name)
+	}
+
+	pub fn slice(&self, name: &str) -> Option<&Slice> {
+		self.slices.iter().find(|slice| slice.name == name)
+	}
+
+	/// Set the anchor of each frame from the first slice with a pivot that applies to it, or else the frame's own `pivot`.
+	/// Frames with neither are anchored at the center of their untrimmed frame, so trimming doesn't move them.
+	pub fn apply_slice_pivots(&mut self) {
+		for (frame_index, frame) in self.frames.iter_mut().enumerate() {
+			let slice_pivot = self.slices.iter()
+				.filter_map(|slice| slice.key_for_frame(frame_index))
+				.find_map(|key| Some(key.start + key.pivot?));
+
+			let pivot = match (slice_pivot, frame.pivot) {
+				(Some(pivot), _) => pivot.as_vec2(),
+				(None, Some(fraction)) => fraction * frame.source_size.as_vec2(),
+				(None, None) => frame.source_size.as_vec2() / 2.0,
+			};
+
+			frame.sprite.anchor = frame.anchor_for(pivot);
+		}
+	}
+}
+
+
+impl FrameTag {
+	/// Fail unless the tag's frames are in order, and all within a sheet of `num_frames` frames.
+	pub(crate) fn check_frames(&self, num_frames: usize) -> anyhow::Result<()> {
+		let (from, to) = (*self.frames.start(), *self.frames.end());
+
+		if from > to || to >= num_frames {
+			anyhow::bail!("Tag '{}' refers to frames {from}..={to}, but there are only {num_frames} frames", self.name);
+		}
+
+		Ok(())
+	}
+}
+
+
+impl SpriteFrame {
+	/// Convert a point in the untrimmed frame, in pixels from the top-left, into an anchor for `sprite`.
+	fn anchor_for(&self, point: Vec2) -> Anchor {
+		let local = point - self.trim_offset.as_vec2();
+		Anchor::Pixels(Vec2::new(local.x, self.sprite.size.y as f32 - local.y))
+	}
+}
+
+
+impl Slice {
+	pub fn key_for_frame(&self, frame: usize) -> Option<&SliceKey> {
+		self.keys.iter()
+			.take_while(|key| key.frame <= frame)
+			.last()
+	}
+}
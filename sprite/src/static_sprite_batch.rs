@@ -0,0 +1,211 @@
+use glam::{Vec4, Mat3, IVec2};
+use std::ops::Range;
+use crate::{AtlasTexture, BlendMode, Dither, Mesh, MeshBuilder, Sprite, SpriteShaders, SpriteVertex, TexturedVertex};
+use crate::dither::DitherTextures;
+use crate::sprite_renderer::{quad_vertices, QUAD_INDICES};
+
+
+/// Identifies a quad previously added to a `StaticSpriteBatch`. Ids from before the batch was last cleared are stale,
+/// and are ignored by `update_quad`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StaticSpriteId {
+	index: usize,
+	/// Which `clear` of the batch this id was handed out after.
+	generation: u32,
+}
+
+
+/// A set of sprite quads that is built once and then kept on the GPU between frames.
+///
+/// Unlike `SpriteRenderer`, nothing is cleared after `draw`. Quads can be moved or changed after the fact with
+/// `update_quad`, in which case only the vertices that changed are uploaded on the next `draw`.
+///
+/// The whole batch is drawn with one blend mode and dither, set up in `draw` the same way `SpriteRenderer` sets up each
+/// of its batches. Quads aren't sorted, so transparent blend modes only look right if they don't overlap.
+pub struct StaticSpriteBatch<V: SpriteVertex = TexturedVertex> {
+	builder: MeshBuilder<V>,
+	mesh: Mesh<V>,
+
+	/// What each quad was built from, so they can be rebuilt if the texture changes size.
+	quads: Vec<(Sprite, Mat3, Vec4)>,
+
+	texture_handle: u32,
+	texture_size: IVec2,
+
+	/// Bumped by `clear`, to tell stale ids apart.
+	generation: u32,
+
+	blend_mode: BlendMode,
+	programs: Option<[u32; BlendMode::ALL.len()]>,
+
+	dither: Dither,
+	dither_textures: DitherTextures,
+	/// Counts calls to `draw`, for `Dither::temporal`.
+	frame: u32,
+
+	needs_full_upload: bool,
+	dirty_quads: Option<Range<usize>>,
+}
+
+impl StaticSpriteBatch {
+	pub fn new(texture: &impl AtlasTexture) -> StaticSpriteBatch {
+		StaticSpriteBatch::with_vertex_format(texture)
+	}
+}
+
+impl<V: SpriteVertex> StaticSpriteBatch<V> {
+	pub fn with_vertex_format(texture: &impl AtlasTexture) -> StaticSpriteBatch<V> {
+		StaticSpriteBatch {
+			builder: MeshBuilder::new(),
+			mesh: Mesh::new(),
+
+			quads: Vec::new(),
+
+			texture_handle: texture.handle(),
+			texture_size: texture.size(),
+
+			generation: 0,
+
+			blend_mode: BlendMode::default(),
+			programs: None,
+
+			dither: Dither::default(),
+			dither_textures: DitherTextures::default(),
+			frame: 0,
+
+			needs_full_upload: false,
+			dirty_quads: None,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.builder.vertices.len() / 4
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.builder.vertices.is_empty()
+	}
+
+	/// Remove all quads from the batch. Ids of the removed quads become stale.
+	pub fn clear(&mut self) {
+		self.builder.clear();
+		self.quads.clear();
+		self.generation = self.generation.wrapping_add(1);
+		self.needs_full_upload = true;
+		self.dirty_quads = None;
+	}
+
+	pub fn quad_raw(&mut self, sprite: &Sprite, transform: &Mat3, color: Vec4) -> StaticSpriteId {
+		let id = StaticSpriteId { index: self.len(), generation: self.generation };
+		let new_vertices = quad_vertices(sprite, transform, color, self.texture_size);
+		self.builder.append(new_vertices, QUAD_INDICES);
+		self.quads.push((*sprite, *transform, color));
+		self.needs_full_upload = true;
+		id
+	}
+
+	/// Replace a quad previously added with `quad_raw`.
+	/// This is also how to change a quad's color, e.g., to fade it out.
+	/// Returns false, changing nothing, if `id` is stale - from before the batch was last cleared.
+	pub fn update_quad(&mut self, id: StaticSpriteId, sprite: &Sprite, transform: &Mat3, color: Vec4) -> bool {
+		let StaticSpriteId { index: quad_index, generation } = id;
+
+		if generation != self.generation || quad_index >= self.quads.len() {
+			return false
+		}
+
+		let new_vertices = quad_vertices(sprite, transform, color, self.texture_size);
+
+		let first_vertex = quad_index * 4;
+		self.builder.vertices[first_vertex..first_vertex + 4].copy_from_slice(&new_vertices);
+		self.quads[quad_index] = (*sprite, *transform, color);
+
+		self.dirty_quads = match self.dirty_quads.take() {
+			Some(range) => Some(range.start.min(quad_index) .. range.end.max(quad_index + 1)),
+			None => Some(quad_index .. quad_index + 1),
+		};
+
+		true
+	}
+
+	/// Draw from `texture` instead. If its size differs, every quad's uvs are rebuilt so its sprite still covers
+	/// the same pixels - e.g., after a `DynamicAtlas` grows. Nothing notices the atlas changing on its own, so this has
+	/// to be called after every change - see `DynamicAtlas::generation`.
+	pub fn set_texture(&mut self, texture: &impl AtlasTexture) {
+		self.texture_handle = texture.handle();
+
+		if self.texture_size == texture.size() {
+			return
+		}
+
+		self.texture_size = texture.size();
+
+		for (quad_index, (sprite, transform, color)) in self.quads.iter().enumerate() {
+			let first_vertex = quad_index * 4;
+			let new_vertices = quad_vertices(sprite, transform, *color, self.texture_size);
+			self.builder.vertices[first_vertex..first_vertex + 4].copy_from_slice(&new_vertices);
+		}
+
+		if !self.quads.is_empty() {
+			self.dirty_quads = Some(0..self.quads.len());
+		}
+	}
+
+	/// Draw the batch with `blend_mode`. Stays set until changed. Defaults to `BlendMode::Dithered`.
+	pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+		self.blend_mode = blend_mode;
+	}
+
+	/// Dither the batch as described by `dither`, when drawn with `BlendMode::Dithered`. Defaults to `Dither::default()`.
+	pub fn set_dither(&mut self, dither: Dither) {
+		self.dither = dither;
+	}
+
+	/// Bind the program from `shaders` matching the blend mode in `draw`. Otherwise `draw` uses whichever program is
+	/// bound, which should match the blend mode.
+	/// Only the program handles are kept, so `shaders` must outlive any following `draw`s.
+	pub fn set_shaders(&mut self, shaders: &SpriteShaders) {
+		self.programs = Some(shaders.programs());
+	}
+
+	pub fn draw(&mut self) {
+		if self.needs_full_upload {
+			// Quads have been added or removed since the last upload, so the buffers need to be reallocated.
+			self.mesh.upload(&self.builder, gl::STATIC_DRAW);
+			self.needs_full_upload = false;
+			self.dirty_quads = None;
+
+		} else if let Some(dirty_quads) = self.dirty_quads.take() {
+			let vertex_range = dirty_quads.start * 4 .. dirty_quads.end * 4;
+			self.mesh.update_vertices(vertex_range.start, &self.builder.vertices[vertex_range]);
+		}
+
+		unsafe {
+			gl::BindTextureUnit(0, self.texture_handle);
+
+			if let Some(programs) = &self.programs {
+				gl::UseProgram(programs[self.blend_mode as usize]);
+			}
+
+			// Program uniforms persist, so these have to be set even if the dither hasn't changed - a `SpriteRenderer`
+			// sharing the program may have drawn with another one since.
+			if self.blend_mode == BlendMode::Dithered {
+				self.dither_textures.bind(&self.dither, self.frame);
+			}
+
+			gl::DepthMask(if self.blend_mode.is_transparent() { gl::FALSE } else { gl::TRUE });
+		}
+
+		self.blend_mode.apply();
+		self.mesh.draw();
+
+		// Leave things as we found them, for anything drawn after us.
+		BlendMode::Opaque.apply();
+
+		unsafe {
+			gl::DepthMask(gl::TRUE);
+		}
+
+		self.frame = self.frame.wrapping_add(1);
+	}
+}
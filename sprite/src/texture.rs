@@ -0,0 +1,401 @@
+use glam::IVec2;
+use std::path::Path;
+
+
+/// How texel data is stored, and how the shader should interpret it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+	/// RGBA, with colour channels converted from sRGB to linear when sampled. For anything meant to be looked at.
+	Srgb,
+
+	/// RGBA, sampled as-is. For normal maps and other data textures.
+	Linear,
+
+	/// A single linear channel, sampled into `.r`. For masks, noise, and other scalar data.
+	SingleChannel,
+
+	/// `SingleChannel` with 16 bits per texel, for data that needs more than 256 levels.
+	/// Pixels are passed as native-endian `u16`s.
+	SingleChannel16,
+}
+
+impl TextureFormat {
+	/// The size of a pixel as passed to `Texture::from_pixels`.
+	pub fn bytes_per_pixel(self) -> usize {
+		PixelFormat::from(self).bytes_per_pixel
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+	Nearest,
+	Linear,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureWrap {
+	Repeat,
+	MirroredRepeat,
+	ClampToEdge,
+}
+
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextureOptions {
+	pub format: TextureFormat,
+	pub min_filter: TextureFilter,
+	pub mag_filter: TextureFilter,
+	pub wrap: TextureWrap,
+
+	/// Allocate and generate a full mip chain.
+	/// When set, `min_filter` also determines how samples from neighbouring mip levels are blended.
+	pub mipmaps: bool,
+}
+
+impl Default for TextureOptions {
+	/// Options suitable for pixel art.
+	fn default() -> Self {
+		TextureOptions {
+			format: TextureFormat::Srgb,
+			min_filter: TextureFilter::Nearest,
+			mag_filter: TextureFilter::Nearest,
+			wrap: TextureWrap::Repeat,
+			mipmaps: false,
+		}
+	}
+}
+
+
+
+/// A 2D texture that owns its GL handle, and remembers its size.
+pub struct Texture {
+	handle: u32,
+	size: IVec2,
+	format: TextureFormat,
+}
+
+impl Texture {
+	pub fn load(path: impl AsRef<Path>, options: &TextureOptions) -> anyhow::Result<Texture> {
+		let image = image::open(path)?;
+		Ok(Texture::from_image(image, options))
+	}
+
+	/// Decode an encoded image (e.g., the contents of a png file), guessing its format from its contents.
+	pub fn from_memory(encoded: &[u8], options: &TextureOptions) -> anyhow::Result<Texture> {
+		let image = image::load_from_memory(encoded)?;
+		Ok(Texture::from_image(image, options))
+	}
+
+	/// Upload an already decoded image, top row first.
+	pub fn from_image(image: image::DynamicImage, options: &TextureOptions) -> Texture {
+		// Images are stored top row first, but GL expects the bottom row first.
+		let image = image.flipv();
+		let size = IVec2::new(image.width() as i32, image.height() as i32);
+
+		Texture::from_pixels(size, &image_pixels(image, options.format), options)
+	}
+
+	/// Create a texture from raw pixel data, bottom row first.
+	/// `pixels` is expected to be tightly packed RGBA8, R8 for `TextureFormat::SingleChannel`, or R16 for
+	/// `TextureFormat::SingleChannel16`.
+	pub fn from_pixels(size: IVec2, pixels: &[u8], options: &TextureOptions) -> Texture {
+		let texture = Texture::allocate(size, options);
+		texture.upload_region(IVec2::ZERO, size, pixels);
+
+		unsafe {
+			apply_sampling_options(texture.handle, options);
+		}
+
+		texture
+	}
+
+	/// Create a texture with every texel cleared to zero, to be filled in later with `upload_region`.
+	pub fn empty(size: IVec2, options: &TextureOptions) -> Texture {
+		let texture = Texture::allocate(size, options);
+		texture.clear();
+
+		unsafe {
+			apply_sampling_options(texture.handle, options);
+		}
+
+		texture
+	}
+
+	fn allocate(size: IVec2, options: &TextureOptions) -> Texture {
+		let PixelFormat {internal_format, ..} = PixelFormat::from(options.format);
+
+		unsafe {
+			let mut handle = 0;
+			gl::CreateTextures(gl::TEXTURE_2D, 1, &mut handle);
+
+			// Allocate storage
+			gl::TextureStorage2D(handle, mip_levels(size, options), internal_format, size.x, size.y);
+
+			Texture { handle, size, format: options.format }
+		}
+	}
+
+	/// Overwrite the pixels of level 0 from `start` to `start + size`, measured from the bottom-left.
+	/// `pixels` is laid out as for `from_pixels`. Mipmaps are not regenerated.
+	pub fn upload_region(&self, start: IVec2, size: IVec2, pixels: &[u8]) {
+		let PixelFormat {upload_format, upload_type, bytes_per_pixel, ..} = PixelFormat::from(self.format);
+
+		assert_eq!(pixels.len(), (size.x * size.y) as usize * bytes_per_pixel, "pixel data doesn't match region size");
+		assert!(start.cmpge(IVec2::ZERO).all() && (start + size).cmple(self.size).all(), "region is outside of texture");
+
+		unsafe {
+			// Rows of single channel textures aren't necessarily a multiple of 4 bytes long, which is what GL expects by default.
+			gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+			// Upload image data
+			let level = 0;
+			gl::TextureSubImage2D(
+				self.handle,
+				level, start.x, start.y,
+				size.x, size.y,
+				upload_format,
+				upload_type,
+				pixels.as_ptr() as *const _
+			);
+
+			gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+		}
+	}
+
+	/// Set every texel of level 0 to zero - transparent black.
+	pub fn clear(&self) {
+		let PixelFormat {upload_format, upload_type, ..} = PixelFormat::from(self.format);
+
+		unsafe {
+			// Passing null clears to zero.
+			gl::ClearTexImage(self.handle, 0, upload_format, upload_type, std::ptr::null());
+		}
+	}
+
+	pub fn handle(&self) -> u32 {
+		self.handle
+	}
+
+	pub fn size(&self) -> IVec2 {
+		self.size
+	}
+
+	pub fn format(&self) -> TextureFormat {
+		self.format
+	}
+
+	/// Bind to texture unit `unit` - corresponding to `layout(binding=unit)` in shaders.
+	pub fn bind(&self, unit: u32) {
+		unsafe {
+			gl::BindTextureUnit(unit, self.handle);
+		}
+	}
+}
+
+impl Drop for Texture {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteTextures(1, &self.handle);
+		}
+	}
+}
+
+
+
+/// A `GL_TEXTURE_2D_ARRAY` - a stack of same-sized images that can all be sampled from a single binding.
+/// Shaders address it with a third uv coordinate selecting the layer, and must declare it as a `sampler2DArray`.
+pub struct ArrayTexture {
+	handle: u32,
+	size: IVec2,
+	layers: u32,
+}
+
+impl ArrayTexture {
+	/// Load each image in `paths` into its own layer, in order. All images must be the same size.
+	pub fn load(paths: &[impl AsRef<Path>], options: &TextureOptions) -> anyhow::Result<ArrayTexture> {
+		let images = paths.iter()
+			.map(|path| {
+				let path = path.as_ref();
+				image::open(path).map_err(|err| anyhow::anyhow!("Failed to load '{}': {err}", path.display()))
+			})
+			.collect::<anyhow::Result<Vec<_>>>()?;
+
+		ArrayTexture::from_images(images, options)
+	}
+
+	fn from_images(images: Vec<image::DynamicImage>, options: &TextureOptions) -> anyhow::Result<ArrayTexture> {
+		let Some(first_image) = images.first() else {
+			anyhow::bail!("ArrayTexture requires at least one layer");
+		};
+
+		let size = IVec2::new(first_image.width() as i32, first_image.height() as i32);
+		let layers = images.len() as u32;
+
+		if let Some(mismatched) = images.iter().position(|image| IVec2::new(image.width() as i32, image.height() as i32) != size) {
+			anyhow::bail!("Layer {mismatched} of ArrayTexture doesn't match the size of layer 0 ({size})");
+		}
+
+		let PixelFormat {internal_format, upload_format, upload_type, ..} = PixelFormat::from(options.format);
+
+		unsafe {
+			let mut handle = 0;
+			gl::CreateTextures(gl::TEXTURE_2D_ARRAY, 1, &mut handle);
+
+			// Allocate storage for all layers at once
+			gl::TextureStorage3D(handle, mip_levels(size, options), internal_format, size.x, size.y, layers as i32);
+
+			gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+			for (layer, image) in images.into_iter().enumerate() {
+				// Images are stored top row first, but GL expects the bottom row first.
+				let pixels = image_pixels(image.flipv(), options.format);
+
+				// Upload a single layer - the z offset selects which one.
+				let (level, offset_x, offset_y, offset_layer) = (0, 0, 0, layer as i32);
+				gl::TextureSubImage3D(
+					handle,
+					level, offset_x, offset_y, offset_layer,
+					size.x, size.y, 1,
+					upload_format,
+					upload_type,
+					pixels.as_ptr() as *const _
+				);
+			}
+
+			gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+			apply_sampling_options(handle, options);
+
+			Ok(ArrayTexture { handle, size, layers })
+		}
+	}
+
+	pub fn handle(&self) -> u32 {
+		self.handle
+	}
+
+	/// The size of a single layer.
+	pub fn size(&self) -> IVec2 {
+		self.size
+	}
+
+	pub fn layers(&self) -> u32 {
+		self.layers
+	}
+
+	/// Bind to texture unit `unit` - corresponding to `layout(binding=unit)` in shaders.
+	pub fn bind(&self, unit: u32) {
+		unsafe {
+			gl::BindTextureUnit(unit, self.handle);
+		}
+	}
+}
+
+impl Drop for ArrayTexture {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteTextures(1, &self.handle);
+		}
+	}
+}
+
+
+
+/// Anything sprites can be drawn from. Sprite coordinates are interpreted in pixels of `size`.
+pub trait AtlasTexture {
+	fn handle(&self) -> u32;
+	fn size(&self) -> IVec2;
+}
+
+impl AtlasTexture for Texture {
+	fn handle(&self) -> u32 {
+		self.handle
+	}
+
+	fn size(&self) -> IVec2 {
+		self.size
+	}
+}
+
+impl AtlasTexture for ArrayTexture {
+	fn handle(&self) -> u32 {
+		self.handle
+	}
+
+	fn size(&self) -> IVec2 {
+		self.size
+	}
+}
+
+
+
+struct PixelFormat {
+	internal_format: u32,
+	upload_format: u32,
+	upload_type: u32,
+	bytes_per_pixel: usize,
+}
+
+impl From<TextureFormat> for PixelFormat {
+	fn from(format: TextureFormat) -> PixelFormat {
+		let (internal_format, upload_format, upload_type, bytes_per_pixel) = match format {
+			TextureFormat::Srgb => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE, 4),
+			TextureFormat::Linear => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, 4),
+			TextureFormat::SingleChannel => (gl::R8, gl::RED, gl::UNSIGNED_BYTE, 1),
+			TextureFormat::SingleChannel16 => (gl::R16, gl::RED, gl::UNSIGNED_SHORT, 2),
+		};
+
+		PixelFormat {internal_format, upload_format, upload_type, bytes_per_pixel}
+	}
+}
+
+/// `image`'s pixels converted to `format`, laid out as `Texture::from_pixels` expects.
+fn image_pixels(image: image::DynamicImage, format: TextureFormat) -> Vec<u8> {
+	match format {
+		TextureFormat::SingleChannel => image.into_luma8().into_raw(),
+		TextureFormat::SingleChannel16 => image.into_luma16().into_raw().into_iter().flat_map(u16::to_ne_bytes).collect(),
+		TextureFormat::Srgb | TextureFormat::Linear => image.into_rgba8().into_raw(),
+	}
+}
+
+fn mip_levels(size: IVec2, options: &TextureOptions) -> i32 {
+	match options.mipmaps {
+		true => size.max_element().max(1).ilog2() as i32 + 1,
+		false => 1,
+	}
+}
+
+/// Set filtering and wrap modes, generating mipmaps if requested.
+/// Expects level 0 to already be uploaded.
+unsafe fn apply_sampling_options(handle: u32, options: &TextureOptions) {
+	unsafe {
+		if options.mipmaps {
+			gl::GenerateTextureMipmap(handle);
+		}
+
+		// Set sampling parameters.
+		// If we don't set these we'd need to generate mipmaps, since GL_TEXTURE_MIN_FILTER defaults to GL_NEAREST_MIPMAP_LINEAR
+		let min_filter = match (options.min_filter, options.mipmaps) {
+			(TextureFilter::Nearest, false) => gl::NEAREST,
+			(TextureFilter::Linear, false) => gl::LINEAR,
+			(TextureFilter::Nearest, true) => gl::NEAREST_MIPMAP_LINEAR,
+			(TextureFilter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+		};
+
+		let mag_filter = match options.mag_filter {
+			TextureFilter::Nearest => gl::NEAREST,
+			TextureFilter::Linear => gl::LINEAR,
+		};
+
+		let wrap = match options.wrap {
+			TextureWrap::Repeat => gl::REPEAT,
+			TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+			TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+		};
+
+		gl::TextureParameteri(handle, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+		gl::TextureParameteri(handle, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+		gl::TextureParameteri(handle, gl::TEXTURE_WRAP_S, wrap as i32);
+		gl::TextureParameteri(handle, gl::TEXTURE_WRAP_T, wrap as i32);
+	}
+}
@@ -0,0 +1,434 @@
+use glam::{Vec2, Vec3, Mat3, Mat4};
+use crate::{Anchor, Billboard, Sprite};
+
+
+/// Which plane a `Transform3D` lays its sprite in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpritePlane {
+	/// Upright, facing +Z. Right is +X and up is +Y.
+	XY,
+
+	/// Flat on the ground, facing +Y. Right is +X and up is -Z, so the top of the sprite points away from a default camera.
+	XZ,
+
+	/// Turned to face the camera - see `Billboard`.
+	Billboard(Billboard),
+}
+
+
+/// Where and how to draw a sprite in the XY plane, for 2D scenes.
+///
+/// Converts to the `Mat3` basis consumed by `SpriteRenderer::quad_raw` with `to_mat3`:
+///
+/// ```
+/// # use glam::{Vec2, Vec4, IVec2};
+/// # use sprite::{Sprite, SpriteInstance, TexturedVertex, Transform2D};
+/// // A 32x16 pixel sprite, anchored at its center.
+/// let sprite = Sprite::new(IVec2::ZERO, IVec2::new(32, 16));
+///
+/// // At 16 pixels per unit it is 2x1 units, doubled to 4x2.
+/// let transform = Transform2D::new(Vec2::new(10.0, 0.0)).with_scale(Vec2::splat(2.0));
+/// let matrix = transform.to_mat3(&sprite, 16.0);
+///
+/// let positions = SpriteInstance::new(&sprite, &matrix, Vec4::ONE, IVec2::splat(64))
+///     .vertices::<TexturedVertex>()
+///     .map(|vertex| vertex.position.truncate());
+///
+/// assert_eq!(positions, [
+///     Vec2::new(8.0, -1.0),
+///     Vec2::new(8.0, 1.0),
+///     Vec2::new(12.0, 1.0),
+///     Vec2::new(12.0, -1.0),
+/// ]);
+///
+/// // Flipping mirrors the sprite around its pivot, and rotation turns it counter-clockwise around the same point.
+/// let matrix = transform.with_flip_x(true).with_rotation(std::f32::consts::FRAC_PI_2).to_mat3(&sprite, 16.0);
+///
+/// let positions = SpriteInstance::new(&sprite, &matrix, Vec4::ONE, IVec2::splat(64))
+///     .vertices::<TexturedVertex>()
+///     .map(|vertex| vertex.position.truncate().round());
+///
+/// assert_eq!(positions, [
+///     Vec2::new(11.0, 2.0),
+///     Vec2::new(9.0, 2.0),
+///     Vec2::new(9.0, -2.0),
+///     Vec2::new(11.0, -2.0),
+/// ]);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform2D {
+	/// Where the sprite's pivot goes.
+	pub position: Vec2,
+
+	/// Z coordinate of the quad, for depth testing against other sprites.
+	pub depth: f32,
+
+	/// Counter-clockwise rotation around the pivot, in radians.
+	pub rotation: f32,
+
+	/// Multiplies the sprite's size in world units.
+	pub scale: Vec2,
+
+	pub flip_x: bool,
+	pub flip_y: bool,
+
+	/// Replaces the sprite's anchor.
+	pub pivot: Option<Anchor>,
+}
+
+/// Where and how to draw a sprite in 3D. The 3D counterpart of `Transform2D`.
+///
+/// ```
+/// # use glam::{Vec3, Vec4, Mat4, IVec2};
+/// # use sprite::{Anchor, Sprite, SpriteInstance, SpritePlane, TexturedVertex, Transform3D};
+/// let sprite = Sprite::new(IVec2::ZERO, IVec2::new(16, 16));
+///
+/// // Lying on the floor, pivoting around its bottom-left corner.
+/// let transform = Transform3D::new(Vec3::new(0.0, -1.0, 0.0))
+///     .with_plane(SpritePlane::XZ)
+///     .with_pivot(Anchor::BottomLeft);
+///
+/// // The camera is only needed for billboards.
+/// let matrix = transform.to_mat3(&sprite, 16.0, &Mat4::IDENTITY);
+///
+/// let positions = SpriteInstance::new(&sprite, &matrix, Vec4::ONE, IVec2::splat(64))
+///     .vertices::<TexturedVertex>()
+///     .map(|vertex| vertex.position);
+///
+/// assert_eq!(positions, [
+///     Vec3::new(0.0, -1.0, 0.0),
+///     Vec3::new(0.0, -1.0, -1.0),
+///     Vec3::new(1.0, -1.0, -1.0),
+///     Vec3::new(1.0, -1.0, 0.0),
+/// ]);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform3D {
+	/// Where the sprite's pivot goes.
+	pub position: Vec3,
+
+	/// Counter-clockwise rotation within `plane` around the pivot, in radians.
+	pub rotation: f32,
+
+	/// Multiplies the sprite's size in world units.
+	pub scale: Vec2,
+
+	pub flip_x: bool,
+	pub flip_y: bool,
+
+	/// Replaces the sprite's anchor.
+	pub pivot: Option<Anchor>,
+
+	pub plane: SpritePlane,
+}
+
+
+impl Transform2D {
+	pub fn new(position: Vec2) -> Transform2D {
+		Transform2D {
+			position,
+			depth: 0.0,
+			rotation: 0.0,
+			scale: Vec2::ONE,
+			flip_x: false,
+			flip_y: false,
+			pivot: None,
+		}
+	}
+
+	pub fn with_depth(mut self, depth: f32) -> Transform2D {
+		self.depth = depth;
+		self
+	}
+
+	pub fn with_rotation(mut self, rotation: f32) -> Transform2D {
+		self.rotation = rotation;
+		self
+	}
+
+	pub fn with_scale(mut self, scale: Vec2) -> Transform2D {
+		self.scale = scale;
+		self
+	}
+
+	pub fn with_flip_x(mut self, flip_x: bool) -> Transform2D {
+		self.flip_x = flip_x;
+		self
+	}
+
+	pub fn with_flip_y(mut self, flip_y: bool) -> Transform2D {
+		self.flip_y = flip_y;
+		self
+	}
+
+	pub fn with_pivot(mut self, pivot: Anchor) -> Transform2D {
+		self.pivot = Some(pivot);
+		self
+	}
+
+	/// The basis for drawing `sprite` with `quad_raw`, sized at `pixels_per_unit` texture pixels per world unit.
+	pub fn to_mat3(&self, sprite: &Sprite, pixels_per_unit: f32) -> Mat3 {
+		Transform3D::from(*self).to_mat3(sprite, pixels_per_unit, &Mat4::IDENTITY)
+	}
+}
+
+impl Default for Transform2D {
+	fn default() -> Transform2D {
+		Transform2D::new(Vec2::ZERO)
+	}
+}
+
+
+impl Transform3D {
+	/// Upright in the XY plane at `position`.
+	pub fn new(position: Vec3) -> Transform3D {
+		Transform3D {
+			position,
+			rotation: 0.0,
+			scale: Vec2::ONE,
+			flip_x: false,
+			flip_y: false,
+			pivot: None,
+			plane: SpritePlane::XY,
+		}
+	}
+
+	pub fn with_rotation(mut self, rotation: f32) -> Transform3D {
+		self.rotation = rotation;
+		self
+	}
+
+	pub fn with_scale(mut self, scale: Vec2) -> Transform3D {
+		self.scale = scale;
+		self
+	}
+
+	pub fn with_flip_x(mut self, flip_x: bool) -> Transform3D {
+		self.flip_x = flip_x;
+		self
+	}
+
+	pub fn with_flip_y(mut self, flip_y: bool) -> Transform3D {
+		self.flip_y = flip_y;
+		self
+	}
+
+	pub fn with_pivot(mut self, pivot: Anchor) -> Transform3D {
+		self.pivot = Some(pivot);
+		self
+	}
+
+	pub fn with_plane(mut self, plane: SpritePlane) -> Transform3D {
+		self.plane = plane;
+		self
+	}
+
+	/// The basis for drawing `sprite` with `quad_raw`, sized at `pixels_per_unit` texture pixels per world unit.
+	/// `camera_transform` is the inverse of the view matrix, and is only used by `SpritePlane::Billboard`.
+	pub fn to_mat3(&self, sprite: &Sprite, pixels_per_unit: f32, camera_transform: &Mat4) -> Mat3 {
+		let (plane_right, plane_up) = match self.plane {
+			SpritePlane::XY => (Vec3::X, Vec3::Y),
+			SpritePlane::XZ => (Vec3::X, -Vec3::Z),
+			SpritePlane::Billboard(mode) => {
+				let basis = mode.transform(camera_transform, self.position, Vec2::ONE);
+				(basis.x_axis, basis.y_axis)
+			}
+		};
+
+		let (sin, cos) = self.rotation.sin_cos();
+		let right = plane_right * cos + plane_up * sin;
+		let up = plane_up * cos - plane_right * sin;
+
+		let flip = Vec2::new(
+			if self.flip_x { -1.0 } else { 1.0 },
+			if self.flip_y { -1.0 } else { 1.0 },
+		);
+
+		let size = sprite.world_size(pixels_per_unit) * self.scale * flip;
+		let basis = Mat3::from_cols(right * size.x, up * size.y, self.position);
+
+		match self.pivot {
+			// `quad_raw` moves the sprite's own anchor to the origin, so shift by the difference to move the pivot there instead.
+			Some(pivot) => basis * Mat3::from_translation(sprite.anchor_fraction() - pivot.fraction(sprite.size)),
+
+			None => basis,
+		}
+	}
+}
+
+impl Default for Transform3D {
+	fn default() -> Transform3D {
+		Transform3D::new(Vec3::ZERO)
+	}
+}
+
+impl From<Transform2D> for Transform3D {
+	fn from(transform: Transform2D) -> Transform3D {
+		Transform3D {
+			position: transform.position.extend(transform.depth),
+			rotation: transform.rotation,
+			scale: transform.scale,
+			flip_x: transform.flip_x,
+			flip_y: transform.flip_y,
+			pivot: transform.pivot,
+			plane: SpritePlane::XY,
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use glam::{IVec2, Vec4};
+	use super::*;
+	use crate::{SpriteInstance, TexturedVertex};
+
+	/// 32x16 pixels, anchored at its center - 2x1 units at 16 pixels per unit.
+	const SPRITE: Sprite = Sprite::new(IVec2::ZERO, IVec2::new(32, 16));
+	const PIXELS_PER_UNIT: f32 = 16.0;
+
+	/// Where `quad_raw` would put the quad's corners: bottom-left, top-left, top-right, then bottom-right of the sprite.
+	fn corners(transform: Transform3D, camera_transform: &Mat4) -> [Vec3; 4] {
+		let matrix = transform.to_mat3(&SPRITE, PIXELS_PER_UNIT, camera_transform);
+
+		SpriteInstance::new(&SPRITE, &matrix, Vec4::ONE, IVec2::splat(64))
+			.vertices::<TexturedVertex>()
+			.map(|vertex| vertex.position)
+	}
+
+	fn corners_2d(transform: Transform2D) -> [Vec3; 4] {
+		corners(transform.into(), &Mat4::IDENTITY)
+	}
+
+	#[track_caller]
+	fn assert_corners(actual: [Vec3; 4], expected: [Vec3; 4]) {
+		let close = actual.iter().zip(&expected).all(|(a, b)| a.abs_diff_eq(*b, 1e-5));
+		assert!(close, "corners {actual:?} aren't {expected:?}");
+	}
+
+	#[test]
+	fn xy_plane() {
+		assert_corners(corners(Transform3D::new(Vec3::new(1.0, 2.0, 3.0)), &Mat4::IDENTITY), [
+			Vec3::new(0.0, 1.5, 3.0),
+			Vec3::new(0.0, 2.5, 3.0),
+			Vec3::new(2.0, 2.5, 3.0),
+			Vec3::new(2.0, 1.5, 3.0),
+		]);
+
+		// Transform2D is the same, with depth as z.
+		let transform = Transform2D::new(Vec2::new(1.0, 2.0)).with_depth(3.0).with_scale(Vec2::new(0.5, 2.0));
+		assert_corners(corners_2d(transform), [
+			Vec3::new(0.5, 1.0, 3.0),
+			Vec3::new(0.5, 3.0, 3.0),
+			Vec3::new(1.5, 3.0, 3.0),
+			Vec3::new(1.5, 1.0, 3.0),
+		]);
+	}
+
+	#[test]
+	fn xz_plane() {
+		let transform = Transform3D::new(-Vec3::Y).with_plane(SpritePlane::XZ);
+
+		// Up is -Z, so the top edge is further from a default camera.
+		assert_corners(corners(transform, &Mat4::IDENTITY), [
+			Vec3::new(-1.0, -1.0, 0.5),
+			Vec3::new(-1.0, -1.0, -0.5),
+			Vec3::new(1.0, -1.0, -0.5),
+			Vec3::new(1.0, -1.0, 0.5),
+		]);
+	}
+
+	#[test]
+	fn billboard_planes() {
+		// Off to the side at +X, looking back at the origin.
+		let camera_transform = Mat4::look_at_rh(Vec3::new(5.0, 0.0, 0.0), Vec3::ZERO, Vec3::Y).inverse();
+
+		// Facing +X, so the sprite's right is -Z.
+		for mode in [Billboard::Spherical, Billboard::Cylindrical, Billboard::ScreenAligned] {
+			let transform = Transform3D::new(Vec3::ZERO).with_plane(SpritePlane::Billboard(mode));
+
+			assert_corners(corners(transform, &camera_transform), [
+				Vec3::new(0.0, -0.5, 1.0),
+				Vec3::new(0.0, 0.5, 1.0),
+				Vec3::new(0.0, 0.5, -1.0),
+				Vec3::new(0.0, -0.5, -1.0),
+			]);
+		}
+
+		// Rotation turns the sprite within the plane it faces the camera in.
+		let transform = Transform3D::new(Vec3::Y)
+			.with_plane(SpritePlane::Billboard(Billboard::ScreenAligned))
+			.with_rotation(std::f32::consts::FRAC_PI_2);
+
+		assert_corners(corners(transform, &camera_transform), [
+			Vec3::new(0.0, 0.0, -0.5),
+			Vec3::new(0.0, 0.0, 0.5),
+			Vec3::new(0.0, 2.0, 0.5),
+			Vec3::new(0.0, 2.0, -0.5),
+		]);
+	}
+
+	#[test]
+	fn flips_mirror_around_the_pivot() {
+		let transform = Transform2D::default();
+
+		assert_corners(corners_2d(transform.with_flip_x(true)), [
+			Vec3::new(1.0, -0.5, 0.0),
+			Vec3::new(1.0, 0.5, 0.0),
+			Vec3::new(-1.0, 0.5, 0.0),
+			Vec3::new(-1.0, -0.5, 0.0),
+		]);
+
+		assert_corners(corners_2d(transform.with_flip_y(true)), [
+			Vec3::new(-1.0, 0.5, 0.0),
+			Vec3::new(-1.0, -0.5, 0.0),
+			Vec3::new(1.0, -0.5, 0.0),
+			Vec3::new(1.0, 0.5, 0.0),
+		]);
+
+		// Flipped in 3D too, along the plane's axes.
+		let transform = Transform3D::new(Vec3::ZERO).with_plane(SpritePlane::XZ).with_flip_x(true).with_flip_y(true);
+		assert_corners(corners(transform, &Mat4::IDENTITY), [
+			Vec3::new(1.0, 0.0, -0.5),
+			Vec3::new(1.0, 0.0, 0.5),
+			Vec3::new(-1.0, 0.0, 0.5),
+			Vec3::new(-1.0, 0.0, -0.5),
+		]);
+	}
+
+	#[test]
+	fn pivot_replaces_anchor() {
+		let transform = Transform2D::new(Vec2::new(1.0, 1.0)).with_pivot(Anchor::BottomLeft);
+
+		assert_corners(corners_2d(transform), [
+			Vec3::new(1.0, 1.0, 0.0),
+			Vec3::new(1.0, 2.0, 0.0),
+			Vec3::new(3.0, 2.0, 0.0),
+			Vec3::new(3.0, 1.0, 0.0),
+		]);
+
+		// Flipping and rotating both happen around the pivot rather than the sprite's anchor.
+		assert_corners(corners_2d(transform.with_flip_x(true)), [
+			Vec3::new(1.0, 1.0, 0.0),
+			Vec3::new(1.0, 2.0, 0.0),
+			Vec3::new(-1.0, 2.0, 0.0),
+			Vec3::new(-1.0, 1.0, 0.0),
+		]);
+
+		assert_corners(corners_2d(transform.with_rotation(std::f32::consts::PI)), [
+			Vec3::new(1.0, 1.0, 0.0),
+			Vec3::new(1.0, 0.0, 0.0),
+			Vec3::new(-1.0, 0.0, 0.0),
+			Vec3::new(-1.0, 1.0, 0.0),
+		]);
+
+		// A pixel pivot, a quarter of the way in from the bottom-left.
+		let transform = Transform2D::default().with_pivot(Anchor::Pixels(Vec2::new(8.0, 4.0)));
+		assert_corners(corners_2d(transform), [
+			Vec3::new(-0.5, -0.25, 0.0),
+			Vec3::new(-0.5, 0.75, 0.0),
+			Vec3::new(1.5, 0.75, 0.0),
+			Vec3::new(1.5, -0.25, 0.0),
+		]);
+	}
+}
@@ -0,0 +1,126 @@
+use glam::{IVec2, Vec2};
+use image::Rgba;
+use sprite::{Anchor, TagDirection};
+use sprite::aseprite::{load, parse};
+
+
+/// A 4x2 indexed color file with two frames, assembled by hand following the spec:
+/// - an opaque background layer, all palette index 0, which is also the transparent index
+/// - a top layer with a red pixel then a transparent one in frame 0, and a blue pixel in frame 1
+/// - a hidden layer covering everything in blue
+/// - the background cel of frame 1 is linked to frame 0, and the top cel of frame 0 is compressed
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/lamp.aseprite");
+
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+
+#[test]
+fn loads_fixture() {
+	let (sheet, image) = load(FIXTURE).unwrap();
+
+	// Two 4x2 frames side by side.
+	assert_eq!(sheet.size, IVec2::new(8, 2));
+	assert_eq!((image.width(), image.height()), (8, 2));
+
+	let names: Vec<&str> = sheet.frames.iter().map(|frame| frame.name.as_str()).collect();
+	assert_eq!(names, ["lamp 0", "lamp 1"]);
+
+	let rects: Vec<(IVec2, IVec2)> = sheet.frames.iter().map(|frame| (frame.sprite.start, frame.sprite.size)).collect();
+	assert_eq!(rects, [(IVec2::new(0, 0), IVec2::new(4, 2)), (IVec2::new(4, 0), IVec2::new(4, 2))]);
+
+	let durations: Vec<f32> = sheet.frames.iter().map(|frame| frame.duration).collect();
+	assert_eq!(durations, [0.1, 0.25]);
+
+	let blink = sheet.tag("blink").unwrap();
+	assert_eq!(blink.frames, 0..=1);
+	assert_eq!(blink.direction, TagDirection::PingPong);
+	assert_eq!(blink.repeat, Some(2));
+
+	// The slice pivot is at the bottom-center of the frame.
+	let feet = sheet.slice("feet").unwrap().key_for_frame(1).unwrap();
+	assert_eq!((feet.start, feet.size, feet.pivot), (IVec2::ZERO, IVec2::new(4, 2), Some(IVec2::new(2, 2))));
+	assert_eq!(sheet.frames[1].sprite.anchor, Anchor::Pixels(Vec2::new(2.0, 0.0)));
+
+	// The background is opaque despite using the transparent index, and nothing of the hidden layer shows.
+	// The top layer's transparent pixel at (2, 0) leaves the background showing through.
+	let pixels: Vec<Rgba<u8>> = image.pixels().copied().collect();
+	assert_eq!(pixels, [
+		BLACK, RED, BLACK, BLACK, BLACK, BLACK, BLACK, BLACK,
+		BLACK, BLACK, BLACK, BLACK, BLACK, BLACK, BLUE, BLACK,
+	]);
+}
+
+#[test]
+fn zero_sized_cels_are_skipped() {
+	let mut data = std::fs::read(FIXTURE).unwrap();
+
+	// Empty out frame 1's cel on the top layer, leaving its pixel data in place.
+	let cel = find_chunk(&data, 1, CHUNK_CEL, 1);
+	data[cel + 16 .. cel + 18].copy_from_slice(&0u16.to_le_bytes());
+
+	let (_, image) = parse(&data, "lamp").unwrap();
+	assert_eq!(*image.get_pixel(6, 1), BLACK);
+}
+
+#[test]
+fn huge_cels_are_bounded_by_their_data() {
+	let mut data = std::fs::read(FIXTURE).unwrap();
+
+	// Claim frame 0's compressed top cel is as big as a cel can be, with only its two pixels of data.
+	let cel = find_chunk(&data, 0, CHUNK_CEL, 1);
+	data[cel + 16 .. cel + 20].copy_from_slice(&[0xff; 4]);
+
+	let (_, image) = parse(&data, "lamp").unwrap();
+	let row: Vec<Rgba<u8>> = (0..4).map(|x| *image.get_pixel(x, 0)).collect();
+	assert_eq!(row, [BLACK, RED, BLACK, BLACK]);
+}
+
+#[test]
+fn tags_past_the_last_frame_are_rejected() {
+	let mut data = std::fs::read(FIXTURE).unwrap();
+
+	// The first tag's `to` frame comes after the tag count, 8 reserved bytes and its `from` frame.
+	let tags = find_chunk(&data, 0, CHUNK_TAGS, 0);
+	data[tags + 12 .. tags + 14].copy_from_slice(&2u16.to_le_bytes());
+
+	let error = parse(&data, "lamp").unwrap_err().to_string();
+	assert_eq!(error, "Tag 'blink' refers to frames 0..=2, but there are only 2 frames");
+}
+
+#[test]
+fn garbage_is_rejected() {
+	assert!(parse(&[], "empty").is_err());
+	assert!(parse(&[0; 128], "zeroes").is_err());
+
+	let data = std::fs::read(FIXTURE).unwrap();
+	assert!(parse(&data[..200], "truncated").is_err());
+}
+
+
+/// Offset of the data of the `nth` chunk of type `chunk_type` in frame `frame_index`, just past its header.
+fn find_chunk(data: &[u8], frame_index: usize, chunk_type: u16, nth: usize) -> usize {
+	let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+	let u32_at = |offset: usize| u32::from_le_bytes(data[offset .. offset + 4].try_into().unwrap());
+
+	let mut frame = 128;
+	for _ in 0..frame_index {
+		frame += u32_at(frame) as usize;
+	}
+
+	let num_chunks = u32_at(frame + 12);
+	let mut chunk = frame + 16;
+
+	(0..num_chunks)
+		.filter_map(|_| {
+			let start = chunk;
+			chunk += u32_at(start) as usize;
+			(u16_at(start + 4) == chunk_type).then_some(start + 6)
+		})
+		.nth(nth)
+		.expect("fixture is missing chunk")
+}
@@ -0,0 +1,106 @@
+use glam::{IVec2, Vec2};
+use sprite::{Anchor, SpriteSheet, TagDirection};
+use sprite::aseprite::{load_json, parse_json};
+use std::path::Path;
+
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+fn load_fixture(name: &str) -> SpriteSheet {
+	load_json(Path::new(FIXTURES).join(name)).unwrap()
+}
+
+/// Both layouts describe the same sheet, so they're checked the same way.
+fn check_sheet(sheet: &SpriteSheet) {
+	assert_eq!(sheet.size, IVec2::new(64, 32));
+	assert_eq!(sheet.image_path.as_deref(), Some(Path::new(FIXTURES).join("guy.png").as_path()));
+
+	// In file order, not sorted.
+	let names: Vec<&str> = sheet.frames.iter().map(|frame| frame.name.as_str()).collect();
+	assert_eq!(names, ["guy idle", "guy step", "guy jump"]);
+	assert_eq!(sheet.frame_index("guy jump"), Some(2));
+
+	// Rects are flipped to be measured from the bottom-left of the sheet.
+	let rects: Vec<(IVec2, IVec2)> = sheet.frames.iter().map(|frame| (frame.sprite.start, frame.sprite.size)).collect();
+	assert_eq!(rects, [
+		(IVec2::new(0, 16), IVec2::new(16, 16)),
+		(IVec2::new(16, 18), IVec2::new(12, 14)),
+		(IVec2::new(32, 0), IVec2::new(16, 16)),
+	]);
+
+	let durations: Vec<f32> = sheet.frames.iter().map(|frame| frame.duration).collect();
+	assert_eq!(durations, [0.1, 0.15, 0.2]);
+
+	let step = sheet.frame("guy step").unwrap();
+	assert_eq!(step.source_size, IVec2::new(16, 16));
+	assert_eq!(step.trim_offset, IVec2::new(2, 2));
+
+	let walk = sheet.tag("walk").unwrap();
+	assert_eq!(walk.frames, 0..=2);
+	assert_eq!(walk.direction, TagDirection::PingPong);
+	assert_eq!(walk.repeat, Some(3));
+
+	let idle = sheet.tag("idle").unwrap();
+	assert_eq!(idle.frames, 0..=0);
+	assert_eq!(idle.direction, TagDirection::Forward);
+	assert_eq!(idle.repeat, None);
+
+	assert_eq!(sheet.tag("rewind").unwrap().direction, TagDirection::Reverse);
+
+	let feet = sheet.slice("feet").unwrap();
+	let key = feet.key_for_frame(2).unwrap();
+	assert_eq!((key.start, key.size, key.pivot), (IVec2::new(4, 12), IVec2::new(8, 4), Some(IVec2::new(4, 4))));
+	assert!(sheet.slice("hand").unwrap().key_for_frame(1).is_none());
+
+	// The feet pivot is at the bottom-center of the untrimmed frame, wherever the trimmed sprite ended up.
+	let anchors: Vec<Anchor> = sheet.frames.iter().map(|frame| frame.sprite.anchor).collect();
+	assert_eq!(anchors, [
+		Anchor::Pixels(Vec2::new(8.0, 0.0)),
+		Anchor::Pixels(Vec2::new(6.0, 0.0)),
+		Anchor::Pixels(Vec2::new(8.0, 0.0)),
+	]);
+}
+
+#[test]
+fn hash_layout() {
+	check_sheet(&load_fixture("guy-hash.json"));
+}
+
+#[test]
+fn array_layout() {
+	check_sheet(&load_fixture("guy-array.json"));
+}
+
+#[test]
+fn frames_without_slice_pivots_are_anchored_at_their_untrimmed_center() {
+	let json = r#"{
+		"frames": [
+			{ "filename": "a", "frame": { "x": 0, "y": 0, "w": 12, "h": 14 }, "spriteSourceSize": { "x": 2, "y": 2, "w": 12, "h": 14 }, "sourceSize": { "w": 16, "h": 16 } }
+		],
+		"meta": { "size": { "w": 16, "h": 16 } }
+	}"#;
+
+	let sheet = parse_json(json).unwrap();
+	assert_eq!(sheet.frames[0].sprite.anchor, Anchor::Pixels(Vec2::new(6.0, 8.0)));
+	assert_eq!(sheet.frames[0].duration, 0.1);
+}
+
+#[test]
+fn tags_past_the_last_frame_are_rejected() {
+	let json = r#"{
+		"frames": [
+			{ "filename": "a", "frame": { "x": 0, "y": 0, "w": 8, "h": 8 } },
+			{ "filename": "b", "frame": { "x": 8, "y": 0, "w": 8, "h": 8 } }
+		],
+		"meta": {
+			"size": { "w": 16, "h": 8 },
+			"frameTags": [ { "name": "run", "from": 1, "to": 2, "direction": "forward" } ]
+		}
+	}"#;
+
+	let error = parse_json(json).unwrap_err().to_string();
+	assert_eq!(error, "Tag 'run' refers to frames 1..=2, but there are only 2 frames");
+
+	let backwards = json.replace(r#""from": 1, "to": 2"#, r#""from": 1, "to": 0"#);
+	assert!(parse_json(&backwards).unwrap_err().to_string().contains("Tag 'run'"));
+}
@@ -0,0 +1,439 @@
+//! Packs a directory of PNGs into one or more atlases.
+//!
+//! Each atlas is written as a PNG alongside a json file in Aseprite's 'Hash' sprite sheet layout, so it can be
+//! loaded with `sprite::aseprite::load_json` and sprites looked up by file stem.
+//!
+//! Run with `cargo run -p atlas_packer -- <input dir> <output path> [options]`, e.g.
+//! `cargo run -p atlas_packer -- sprite/assets/sprites sprite/assets/atlas`.
+
+use glam::IVec2;
+use image::RgbaImage;
+use sprite::packing::{RectPacker, SkylinePacker, MaxRectsPacker};
+use std::path::{Path, PathBuf};
+
+
+const USAGE: &str = "\
+usage: atlas_packer <input dir> <output path> [options]
+
+Writes <output path>.png and <output path>.json, or <output path>-N.png/json for each atlas if everything
+doesn't fit into one.
+
+options:
+	--max-size <px>         Maximum width and height of each atlas. Default 2048.
+	--padding <px>          Empty space between sprites. Default 2.
+	--extrude <px>          Repeat the edge pixels of each sprite outwards, to prevent bleeding when filtering. Default 0.
+	--trim                  Remove fully transparent edges, recording where the sprite sat in the original image.
+	--power-of-two          Round atlas dimensions up to powers of two.
+	--packer <name>         'maxrects' (default) or 'skyline'.
+";
+
+
+#[derive(Copy, Clone, Debug)]
+enum PackerKind {
+	Skyline,
+	MaxRects,
+}
+
+#[derive(Debug)]
+struct Options {
+	input: PathBuf,
+	output: PathBuf,
+
+	max_size: i32,
+	padding: i32,
+	extrude: i32,
+	trim: bool,
+	power_of_two: bool,
+	packer: PackerKind,
+}
+
+/// An input image, after trimming.
+struct SourceImage {
+	name: String,
+	pixels: RgbaImage,
+
+	/// The size of the image before trimming.
+	source_size: IVec2,
+
+	/// Where `pixels` sits within the untrimmed image, in pixels from the top-left.
+	trim_offset: IVec2,
+}
+
+struct Atlas {
+	packer: Box<dyn RectPacker>,
+
+	/// Indices into the source images, and where each was placed, including extrusion.
+	placements: Vec<(usize, IVec2)>,
+}
+
+
+fn main() {
+	if let Err(err) = run() {
+		eprintln!("error: {err}");
+		std::process::exit(1);
+	}
+}
+
+fn run() -> anyhow::Result<()> {
+	let options = parse_args(std::env::args().skip(1))?;
+
+	let images = load_images(&options)?;
+	let atlases = pack(&images, &options)?;
+
+	for (atlas_index, atlas) in atlases.iter().enumerate() {
+		let output = match atlases.len() {
+			1 => options.output.clone(),
+			_ => {
+				let mut name = options.output.file_name().unwrap_or_default().to_owned();
+				name.push(format!("-{atlas_index}"));
+				options.output.with_file_name(name)
+			}
+		};
+
+		write_atlas(atlas, &images, &output, &options)?;
+	}
+
+	println!("Packed {} images into {} atlas(es)", images.len(), atlases.len());
+
+	Ok(())
+}
+
+
+fn parse_args(mut args: impl Iterator<Item=String>) -> anyhow::Result<Options> {
+	let mut positional = Vec::new();
+
+	let mut options = Options {
+		input: PathBuf::new(),
+		output: PathBuf::new(),
+
+		max_size: 2048,
+		padding: 2,
+		extrude: 0,
+		trim: false,
+		power_of_two: false,
+		packer: PackerKind::MaxRects,
+	};
+
+	while let Some(arg) = args.next() {
+		let mut value = |name: &str| args.next().ok_or_else(|| anyhow::anyhow!("{name} expects a value\n\n{USAGE}"));
+
+		match arg.as_str() {
+			"--max-size" => options.max_size = value("--max-size")?.parse()?,
+			"--padding" => options.padding = value("--padding")?.parse()?,
+			"--extrude" => options.extrude = value("--extrude")?.parse()?,
+			"--trim" => options.trim = true,
+			"--power-of-two" => options.power_of_two = true,
+			"--packer" => options.packer = match value("--packer")?.as_str() {
+				"skyline" => PackerKind::Skyline,
+				"maxrects" => PackerKind::MaxRects,
+				other => anyhow::bail!("Unknown packer '{other}'\n\n{USAGE}"),
+			},
+
+			"-h" | "--help" => {
+				print!("{USAGE}");
+				std::process::exit(0);
+			}
+
+			_ if arg.starts_with("--") => anyhow::bail!("Unknown option '{arg}'\n\n{USAGE}"),
+			_ => positional.push(PathBuf::from(arg)),
+		}
+	}
+
+	let [input, output] = <[PathBuf; 2]>::try_from(positional)
+		.map_err(|_| anyhow::anyhow!("Expected an input directory and an output path\n\n{USAGE}"))?;
+
+	options.input = input;
+	options.output = output;
+
+	if options.max_size <= 0 || options.padding < 0 || options.extrude < 0 {
+		anyhow::bail!("--max-size must be positive, and --padding and --extrude can't be negative");
+	}
+
+	if options.power_of_two && !(options.max_size as u32).is_power_of_two() {
+		anyhow::bail!("--max-size must be a power of two when using --power-of-two");
+	}
+
+	Ok(options)
+}
+
+
+/// Load every png directly inside the input directory, sorted by name.
+fn load_images(options: &Options) -> anyhow::Result<Vec<SourceImage>> {
+	let mut paths = std::fs::read_dir(&options.input)?
+		.map(|entry| Ok(entry?.path()))
+		.collect::<anyhow::Result<Vec<_>>>()?;
+
+	paths.retain(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")));
+	paths.sort();
+
+	if paths.is_empty() {
+		anyhow::bail!("No png files found in '{}'", options.input.display());
+	}
+
+	check_unique_names(&paths)?;
+
+	paths.iter()
+		.map(|path| {
+			let pixels = image::open(path)
+				.map_err(|err| anyhow::anyhow!("Failed to load '{}': {err}", path.display()))?
+				.into_rgba8();
+
+			let name = frame_name(path);
+			let source_size = IVec2::new(pixels.width() as i32, pixels.height() as i32);
+
+			let (pixels, trim_offset) = match options.trim {
+				true => trim(pixels),
+				false => (pixels, IVec2::ZERO),
+			};
+
+			Ok(SourceImage {name, pixels, source_size, trim_offset})
+		})
+		.collect()
+}
+
+/// Frames are looked up by file stem, so e.g. 'player.png' and 'player.PNG' can't both be packed.
+fn check_unique_names(paths: &[PathBuf]) -> anyhow::Result<()> {
+	let mut seen = std::collections::HashMap::new();
+
+	for path in paths {
+		if let Some(previous) = seen.insert(frame_name(path), path) {
+			anyhow::bail!("'{}' and '{}' would both be named '{}'", previous.display(), path.display(), frame_name(path));
+		}
+	}
+
+	Ok(())
+}
+
+fn frame_name(path: &Path) -> String {
+	path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Crop away fully transparent rows and columns. Fully transparent images are kept as a single pixel.
+fn trim(pixels: RgbaImage) -> (RgbaImage, IVec2) {
+	let opaque = pixels.enumerate_pixels()
+		.filter(|(_, _, pixel)| pixel[3] > 0)
+		.map(|(x, y, _)| IVec2::new(x as i32, y as i32));
+
+	let bounds = opaque.fold(None, |bounds: Option<(IVec2, IVec2)>, point| match bounds {
+		Some((min, max)) => Some((min.min(point), max.max(point))),
+		None => Some((point, point)),
+	});
+
+	let (min, max) = bounds.unwrap_or((IVec2::ZERO, IVec2::ZERO));
+	let size = max - min + IVec2::ONE;
+
+	let trimmed = image::imageops::crop_imm(&pixels, min.x as u32, min.y as u32, size.x as u32, size.y as u32).to_image();
+	(trimmed, min)
+}
+
+
+/// Pack images into as few atlases as needed, largest first.
+fn pack(images: &[SourceImage], options: &Options) -> anyhow::Result<Vec<Atlas>> {
+	let mut order: Vec<usize> = (0..images.len()).collect();
+	order.sort_by_key(|&index| {
+		let size = images[index].pixels.dimensions();
+		std::cmp::Reverse((size.1, size.0))
+	});
+
+	// Padding is reserved on the right and bottom of every sprite, so give the packers that much extra room to let
+	// sprites touch the far edges.
+	let packer_size = IVec2::splat(options.max_size + options.padding);
+
+	let mut atlases: Vec<Atlas> = Vec::new();
+
+	for index in order {
+		let image = &images[index];
+		let size = IVec2::new(image.pixels.width() as i32, image.pixels.height() as i32);
+		let reserved_size = size + 2 * options.extrude + options.padding;
+
+		let placed = atlases.iter_mut()
+			.find_map(|atlas| Some((atlas.packer.insert(reserved_size)?, atlas)));
+
+		match placed {
+			Some((position, atlas)) => atlas.placements.push((index, position)),
+
+			None => {
+				let mut packer: Box<dyn RectPacker> = match options.packer {
+					PackerKind::Skyline => Box::new(SkylinePacker::new(packer_size)),
+					PackerKind::MaxRects => Box::new(MaxRectsPacker::new(packer_size)),
+				};
+
+				let Some(position) = packer.insert(reserved_size) else {
+					anyhow::bail!("'{}' ({size}) doesn't fit in a {} pixel atlas", image.name, options.max_size);
+				};
+
+				atlases.push(Atlas {
+					packer,
+					placements: vec![(index, position)],
+				});
+			}
+		}
+	}
+
+	Ok(atlases)
+}
+
+
+fn write_atlas(atlas: &Atlas, images: &[SourceImage], output: &Path, options: &Options) -> anyhow::Result<()> {
+	let image_path = output.with_extension("png");
+	let json_path = output.with_extension("json");
+
+	let image_name = image_path.file_name().unwrap_or_default().to_string_lossy();
+	let (atlas_image, json) = build_atlas(atlas, images, &image_name, options);
+
+	atlas_image.save(&image_path)
+		.map_err(|err| anyhow::anyhow!("Failed to write '{}': {err}", image_path.display()))?;
+
+	std::fs::write(&json_path, serde_json::to_string_pretty(&json)?)
+		.map_err(|err| anyhow::anyhow!("Failed to write '{}': {err}", json_path.display()))?;
+
+	Ok(())
+}
+
+/// Copy the packed images into a new atlas image, and describe where they went in json that refers to `image_name`.
+fn build_atlas(atlas: &Atlas, images: &[SourceImage], image_name: &str, options: &Options) -> (RgbaImage, serde_json::Value) {
+	// Shrink the atlas to fit what was actually packed.
+	let mut atlas_size = atlas.placements.iter()
+		.map(|&(index, position)| {
+			let size = IVec2::new(images[index].pixels.width() as i32, images[index].pixels.height() as i32);
+			position + size + 2 * options.extrude
+		})
+		.fold(IVec2::ONE, IVec2::max);
+
+	if options.power_of_two {
+		atlas_size = IVec2::new((atlas_size.x as u32).next_power_of_two() as i32, (atlas_size.y as u32).next_power_of_two() as i32);
+	}
+
+	let mut atlas_image = RgbaImage::new(atlas_size.x as u32, atlas_size.y as u32);
+	let mut frames = serde_json::Map::new();
+
+	for &(index, position) in &atlas.placements {
+		let image = &images[index];
+		let size = IVec2::new(image.pixels.width() as i32, image.pixels.height() as i32);
+		let start = position + options.extrude;
+
+		// Copy the image, clamping to its edges to fill the extruded border.
+		for y in -options.extrude .. size.y + options.extrude {
+			for x in -options.extrude .. size.x + options.extrude {
+				let source = IVec2::new(x, y).clamp(IVec2::ZERO, size - 1);
+				let pixel = *image.pixels.get_pixel(source.x as u32, source.y as u32);
+				atlas_image.put_pixel((start.x + x) as u32, (start.y + y) as u32, pixel);
+			}
+		}
+
+		frames.insert(image.name.clone(), serde_json::json!({
+			"frame": { "x": start.x, "y": start.y, "w": size.x, "h": size.y },
+			"rotated": false,
+			"trimmed": size != image.source_size,
+			"spriteSourceSize": { "x": image.trim_offset.x, "y": image.trim_offset.y, "w": size.x, "h": size.y },
+			"sourceSize": { "w": image.source_size.x, "h": image.source_size.y },
+			"duration": 100,
+		}));
+	}
+
+	let json = serde_json::json!({
+		"frames": frames,
+		"meta": {
+			"app": "atlas_packer",
+			"image": image_name,
+			"format": "RGBA8888",
+			"size": { "w": atlas_size.x, "h": atlas_size.y },
+			"scale": "1",
+		},
+	});
+
+	(atlas_image, json)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::Rgba;
+
+	fn options(trim: bool) -> Options {
+		Options {
+			input: PathBuf::new(),
+			output: PathBuf::new(),
+
+			max_size: 64,
+			padding: 2,
+			extrude: 1,
+			trim,
+			power_of_two: false,
+			packer: PackerKind::MaxRects,
+		}
+	}
+
+	/// A `size` image filled with `color`, inside `border` transparent pixels on every side.
+	fn source_image(name: &str, size: IVec2, border: i32, color: [u8; 4], options: &Options) -> SourceImage {
+		let full_size = size + 2 * border;
+		let pixels = RgbaImage::from_fn(full_size.x as u32, full_size.y as u32, |x, y| {
+			let inside = IVec2::new(x as i32, y as i32).cmpge(IVec2::splat(border)).all()
+				&& IVec2::new(x as i32, y as i32).cmplt(size + border).all();
+
+			match inside {
+				true => Rgba(color),
+				false => Rgba([0; 4]),
+			}
+		});
+
+		let (pixels, trim_offset) = match options.trim {
+			true => trim(pixels),
+			false => (pixels, IVec2::ZERO),
+		};
+
+		SourceImage {name: name.into(), pixels, source_size: full_size, trim_offset}
+	}
+
+	#[test]
+	fn json_round_trips_through_sprite_sheet() {
+		let options = options(true);
+		let images = [
+			source_image("wide", IVec2::new(20, 6), 0, [255, 0, 0, 255], &options),
+			source_image("padded", IVec2::new(5, 7), 3, [0, 255, 0, 255], &options),
+			source_image("tall", IVec2::new(4, 30), 0, [0, 0, 255, 255], &options),
+		];
+
+		let atlases = pack(&images, &options).unwrap();
+		assert_eq!(atlases.len(), 1);
+
+		let (atlas_image, json) = build_atlas(&atlases[0], &images, "atlas.png", &options);
+		let sheet = sprite::aseprite::parse_json(&serde_json::to_string_pretty(&json).unwrap()).unwrap();
+
+		assert_eq!(sheet.size, IVec2::new(atlas_image.width() as i32, atlas_image.height() as i32));
+		assert_eq!(sheet.image_path, Some(PathBuf::from("atlas.png")));
+		assert_eq!(sheet.frames.len(), images.len());
+
+		for image in &images {
+			let frame = sheet.frame(&image.name).unwrap();
+			let size = IVec2::new(image.pixels.width() as i32, image.pixels.height() as i32);
+
+			assert_eq!(frame.sprite.size, size, "{}", image.name);
+			assert_eq!(frame.source_size, image.source_size, "{}", image.name);
+			assert_eq!(frame.trim_offset, image.trim_offset, "{}", image.name);
+
+			// Sprites are measured from the bottom-left, so flip back to find the pixels in the atlas image.
+			let top = sheet.size.y - frame.sprite.start.y - size.y;
+			for y in 0..size.y {
+				for x in 0..size.x {
+					let atlas_pixel = atlas_image.get_pixel((frame.sprite.start.x + x) as u32, (top + y) as u32);
+					assert_eq!(atlas_pixel, image.pixels.get_pixel(x as u32, y as u32), "{} at {x}, {y}", image.name);
+				}
+			}
+		}
+
+		let padded = sheet.frame("padded").unwrap();
+		assert_eq!((padded.trim_offset, padded.source_size), (IVec2::splat(3), IVec2::new(11, 13)));
+	}
+
+	#[test]
+	fn duplicate_names_are_rejected() {
+		let paths = [PathBuf::from("sprites/player.PNG"), PathBuf::from("sprites/player.png")];
+		let err = check_unique_names(&paths).unwrap_err().to_string();
+
+		assert!(err.contains("player.PNG") && err.contains("player.png"), "{err}");
+		assert!(check_unique_names(&[PathBuf::from("a.png"), PathBuf::from("b.png")]).is_ok());
+	}
+}